@@ -0,0 +1,17 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tetris_rs::headless::LockBench;
+
+// How long lock_piece takes, including the clear_lines work when the I
+// completes rows. Run it with `cargo bench`
+fn lock_piece(c: &mut Criterion) {
+    for (name, clears) in [
+        ("lock_piece with line clears", true),
+        ("lock_piece without line clears", false),
+    ] {
+        let mut bench = LockBench::new(clears);
+        c.bench_function(name, |b| b.iter(|| black_box(bench.lock())));
+    }
+}
+
+criterion_group!(benches, lock_piece);
+criterion_main!(benches);
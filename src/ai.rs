@@ -0,0 +1,132 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{rotated, TetrisGame, BOARD_WIDTH};
+
+// Applied to moves that top out, so the bot only picks one when nothing else is left
+const GAME_OVER_PENALTY: f32 = -1.0e6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Difficulty {
+    // Plays its best move half of the time and a random one otherwise
+    Easy,
+    // Plays its best move four times out of five
+    Medium,
+    // Always plays the greedy best move for the current piece
+    Hard,
+    // Also considers every placement of the next piece before committing
+    Perfect,
+}
+
+// Where the bot wants the current piece: clockwise quarter turns, then its column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub rotation: usize,
+    pub x: usize,
+}
+
+// Scores boards as a weighted sum of features, higher is better
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiBot {
+    pub difficulty: Difficulty,
+    pub w_holes: f32,
+    pub w_bumpiness: f32,
+    pub w_height: f32,
+    pub w_lines_cleared: f32,
+}
+
+impl Default for AiBot {
+    // The weights from Dellacherie's hand-tuned evaluation
+    fn default() -> Self {
+        AiBot {
+            difficulty: Difficulty::Hard,
+            w_holes: -0.51,
+            w_bumpiness: -0.18,
+            w_height: -0.51,
+            w_lines_cleared: 0.76,
+        }
+    }
+}
+
+impl AiBot {
+    pub fn choose(&self, game: &TetrisGame) -> Option<Placement> {
+        let candidates = placements(game);
+        let optimal_rate = match self.difficulty {
+            Difficulty::Easy => 0.5,
+            Difficulty::Medium => 0.8,
+            Difficulty::Hard | Difficulty::Perfect => 1.0,
+        };
+        let mut rng = rand::thread_rng();
+        if !candidates.is_empty() && !rng.gen_bool(optimal_rate) {
+            return Some(candidates[rng.gen_range(0..candidates.len())].0);
+        }
+
+        candidates
+            .iter()
+            .map(|(placement, after)| {
+                let score = if self.difficulty == Difficulty::Perfect && !after.game_over {
+                    placements(after)
+                        .iter()
+                        .map(|(_, next)| self.evaluate(game, next))
+                        .fold(GAME_OVER_PENALTY, f32::max)
+                } else {
+                    self.evaluate(game, after)
+                };
+                (*placement, score)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(placement, _)| placement)
+    }
+
+    // Rates the board `after` reached from `before`
+    pub fn evaluate(&self, before: &TetrisGame, after: &TetrisGame) -> f32 {
+        if after.game_over {
+            return GAME_OVER_PENALTY;
+        }
+        let lines_cleared = after.lines_cleared - before.lines_cleared;
+        self.w_holes * after.board_holes() as f32
+            + self.w_bumpiness * after.bumpiness() as f32
+            + self.w_height * after.aggregate_height() as f32
+            + self.w_lines_cleared * lines_cleared as f32
+    }
+}
+
+// Every distinct rotation and column the current piece can be dropped from,
+// with the shape it has there
+pub fn drop_spots(game: &TetrisGame) -> Vec<(Placement, Vec<Vec<bool>>)> {
+    let piece = &game.current_piece;
+    let mut shapes: Vec<Vec<Vec<bool>>> = Vec::new();
+    let mut shape = piece.shape.clone();
+    for _ in 0..4 {
+        if !shapes.contains(&shape) {
+            shapes.push(shape.clone());
+        }
+        shape = rotated(&shape);
+    }
+
+    let mut result = Vec::new();
+    for (rotation, shape) in shapes.into_iter().enumerate() {
+        let width = shape[0].len();
+        for x in 0..=BOARD_WIDTH - width {
+            if !game.shape_collides(&shape, x, piece.y) {
+                result.push((Placement { rotation, x }, shape.clone()));
+            }
+        }
+    }
+    result
+}
+
+// Each drop spot paired with the game as it would be right after dropping there
+pub fn placements(game: &TetrisGame) -> Vec<(Placement, TetrisGame)> {
+    drop_spots(game)
+        .into_iter()
+        .map(|(placement, shape)| {
+            let mut after = game.clone();
+            after.current_piece.shape = shape;
+            after.current_piece.x = placement.x;
+            after.hard_drop();
+            (placement, after)
+        })
+        .collect()
+}
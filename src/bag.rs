@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::TetrominoType;
+
+// How the next piece is picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Randomizer {
+    // Every piece independently at random
+    Random,
+    // All seven pieces shuffled, dealt out, then reshuffled
+    SevenBag,
+    // Rerolls pieces seen among the last four, like The Grand Master
+    Tgm,
+    // Favors pieces by the square of how long they've been missing
+    DroughtPrevention,
+}
+
+impl Randomizer {
+    pub const ALL: [Randomizer; 4] = [
+        Randomizer::Random,
+        Randomizer::SevenBag,
+        Randomizer::Tgm,
+        Randomizer::DroughtPrevention,
+    ];
+}
+
+const TGM_HISTORY: usize = 4;
+const TGM_REROLLS: usize = 6;
+// The most upcoming pieces the UI can show
+pub const PREVIEW_MAX: usize = 6;
+
+#[derive(Clone)]
+pub struct PieceBag {
+    randomizer: Randomizer,
+    rng: StdRng,
+    bag: Vec<TetrominoType>,
+    history: VecDeque<TetrominoType>,
+    // Pieces drawn since each type last came up, in TetrominoType::ALL order
+    last_seen: [u32; 7],
+    // Dealt but not yet played, each marked with whether it opened a fresh 7-bag
+    upcoming: VecDeque<(TetrominoType, bool)>,
+}
+
+impl PieceBag {
+    pub fn seeded(randomizer: Randomizer, seed: u64) -> Self {
+        let mut bag = PieceBag {
+            randomizer,
+            rng: StdRng::seed_from_u64(seed),
+            bag: Vec::new(),
+            // Starts out as if S and Z had just been dealt, so an S or Z drawn
+            // first is rerolled. It's kept if every reroll is one too, which
+            // makes an S or Z opening rare rather than impossible
+            history: [
+                TetrominoType::Z,
+                TetrominoType::S,
+                TetrominoType::Z,
+                TetrominoType::S,
+            ]
+            .into(),
+            last_seen: [0; 7],
+            upcoming: VecDeque::new(),
+        };
+        bag.refill();
+        bag
+    }
+
+    pub fn next(&mut self) -> TetrominoType {
+        let (piece, _) = self.upcoming.pop_front().unwrap();
+        self.refill();
+        piece
+    }
+
+    // The next `count` pieces, up to PREVIEW_MAX, and whether each opened a 7-bag
+    pub fn preview(&self, count: usize) -> impl Iterator<Item = (TetrominoType, bool)> + '_ {
+        self.upcoming.iter().take(count).copied()
+    }
+
+    fn refill(&mut self) {
+        while self.upcoming.len() < PREVIEW_MAX {
+            let opens_bag = self.randomizer == Randomizer::SevenBag && self.bag.is_empty();
+            let piece = self.deal();
+            self.upcoming.push_back((piece, opens_bag));
+        }
+    }
+
+    fn deal(&mut self) -> TetrominoType {
+        match self.randomizer {
+            Randomizer::Random => self.random_piece(),
+            Randomizer::SevenBag => {
+                if self.bag.is_empty() {
+                    self.bag = TetrominoType::ALL.to_vec();
+                    self.bag.shuffle(&mut self.rng);
+                }
+                self.bag.pop().unwrap()
+            }
+            Randomizer::Tgm => {
+                let mut piece = self.random_piece();
+                for _ in 0..TGM_REROLLS {
+                    if !self.history.contains(&piece) {
+                        break;
+                    }
+                    piece = self.random_piece();
+                }
+                self.history.push_back(piece);
+                if self.history.len() > TGM_HISTORY {
+                    self.history.pop_front();
+                }
+                piece
+            }
+            Randomizer::DroughtPrevention => {
+                let weights = self.last_seen.map(|seen| seen.pow(2) + 1);
+                let index = WeightedIndex::new(weights).unwrap().sample(&mut self.rng);
+                for seen in &mut self.last_seen {
+                    *seen += 1;
+                }
+                self.last_seen[index] = 0;
+                TetrominoType::ALL[index]
+            }
+        }
+    }
+
+    fn random_piece(&mut self) -> TetrominoType {
+        TetrominoType::ALL[self.rng.gen_range(0..TetrominoType::ALL.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tgm_never_alternates_z_and_s_for_long() {
+        let mut bag = PieceBag::seeded(Randomizer::Tgm, 42);
+        let pieces: Vec<TetrominoType> = (0..1000).map(|_| bag.next()).collect();
+        let mut alternations = 0;
+        for pair in pieces.windows(2) {
+            let z_s = matches!(
+                (pair[0], pair[1]),
+                (TetrominoType::Z, TetrominoType::S) | (TetrominoType::S, TetrominoType::Z)
+            );
+            alternations = if z_s { alternations + 1 } else { 0 };
+            assert!(
+                alternations <= 3,
+                "Z and S alternated {alternations} times in a row"
+            );
+        }
+    }
+
+    #[test]
+    fn drought_prevention_keeps_droughts_short() {
+        let mut bag = PieceBag::seeded(Randomizer::DroughtPrevention, 42);
+        let mut drought = [0; 7];
+        for _ in 0..10_000 {
+            let piece = bag.next();
+            for (i, count) in drought.iter_mut().enumerate() {
+                if TetrominoType::ALL[i] == piece {
+                    *count = 0;
+                } else {
+                    *count += 1;
+                    assert!(
+                        *count <= 20,
+                        "{:?} missing for {count} pieces",
+                        TetrominoType::ALL[i]
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CellState, BOARD_HEIGHT, BOARD_WIDTH};
+
+// A puzzle: clear `target_lines` before `max_pieces` run out
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChallengeConfig {
+    pub name: String,
+    pub target_lines: u32,
+    pub max_pieces: u32,
+    pub starting_board: Option<Vec<Vec<CellState>>>,
+}
+
+// How challenges are written in challenges.toml. Board rows are listed top to
+// bottom and sit on the floor, with `#` for a garbage cell and `.` for a gap
+#[derive(Deserialize)]
+struct ChallengeEntry {
+    name: String,
+    target_lines: u32,
+    max_pieces: u32,
+    #[serde(default)]
+    board: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChallengeFile {
+    challenge: Vec<ChallengeEntry>,
+}
+
+impl From<ChallengeEntry> for ChallengeConfig {
+    fn from(entry: ChallengeEntry) -> Self {
+        let starting_board = (!entry.board.is_empty()).then(|| {
+            let mut board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+            let top = BOARD_HEIGHT.saturating_sub(entry.board.len());
+            for (row, line) in board[top..].iter_mut().zip(&entry.board) {
+                for (cell, c) in row.iter_mut().zip(line.chars()) {
+                    if c == '#' {
+                        *cell = CellState::Garbage;
+                    }
+                }
+            }
+            board
+        });
+        ChallengeConfig {
+            name: entry.name,
+            target_lines: entry.target_lines,
+            max_pieces: entry.max_pieces,
+            starting_board,
+        }
+    }
+}
+
+pub fn builtin() -> &'static [ChallengeConfig] {
+    static CHALLENGES: OnceLock<Vec<ChallengeConfig>> = OnceLock::new();
+    CHALLENGES.get_or_init(|| {
+        // coverage: ignore
+        let file: ChallengeFile =
+            toml::from_str(include_str!("../challenges.toml")).expect("challenges.toml is valid");
+        file.challenge
+            .into_iter()
+            .map(ChallengeConfig::from)
+            .collect()
+    })
+}
+
+// Each piece adds four cells and a line takes ten, so 0.4 lines per piece is
+// the best anyone can do on an empty board
+pub fn grade(target_lines: u32, lines: u32, pieces_used: u32) -> char {
+    if lines < target_lines {
+        return 'F';
+    }
+    let rate = lines as f32 / pieces_used.max(1) as f32;
+    if rate >= 0.38 {
+        'S'
+    } else if rate >= 0.33 {
+        'A'
+    } else if rate >= 0.27 {
+        'B'
+    } else {
+        'C'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_challenges_parse() {
+        let challenges = builtin();
+        assert_eq!(challenges.len(), 20);
+        for challenge in challenges {
+            assert!(challenge.target_lines > 0 && challenge.max_pieces > 0);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::ai::Difficulty;
+use crate::config::{self, Config};
+use crate::headless::{self, HeadlessDriver};
+use crate::net::{NetworkMode, MAX_SPECTATED};
+use crate::replay::Replay;
+use crate::{CellState, TetrisGame};
+
+#[derive(Parser)]
+#[command(
+    about = "A tetris game in rust",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    // `play` is the default, so its options work without naming it
+    #[command(flatten)]
+    play: PlayArgs,
+    #[arg(
+        long,
+        global = true,
+        value_name = "LEVEL",
+        default_value = "warn",
+        help = "error, warn, info, debug, trace or off"
+    )]
+    log_level: LevelFilter,
+    #[arg(
+        long,
+        global = true,
+        help = "Also write the log to game.log in the data directory"
+    )]
+    log_file: bool,
+}
+
+#[derive(clap::Args)]
+pub struct PlayArgs {
+    #[arg(
+        long,
+        value_name = "PORT",
+        conflicts_with = "connect",
+        help = "Host an online game on this port"
+    )]
+    host: Option<u16>,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Join the online game hosted at this address"
+    )]
+    connect: Option<String>,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Watch the game hosted at this address; can be given twice"
+    )]
+    spectate: Vec<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Open the game window")]
+    Play(PlayArgs),
+    #[command(about = "Have the bot play games without a window and report how it did")]
+    Benchmark {
+        #[arg(long, default_value_t = 1000)]
+        games: usize,
+        #[arg(long, value_enum, default_value = "hard")]
+        ai: Difficulty,
+    },
+    #[command(about = "Watch a saved replay")]
+    Replay {
+        #[arg(long)]
+        file: PathBuf,
+    },
+    #[command(about = "Check that a saved replay plays back to the score it recorded")]
+    ValidateReplay {
+        #[arg(long)]
+        file: PathBuf,
+    },
+    #[command(about = "Print the board after the bot plays a seeded game")]
+    PrintBoard {
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        #[arg(long, default_value_t = 50)]
+        moves: u32,
+    },
+}
+
+impl Args {
+    pub fn command(self) -> Command {
+        self.command.unwrap_or(Command::Play(self.play))
+    }
+
+    // Logs go to stderr, and to game.log with --log-file. Failing to open the
+    // file still leaves stderr logging set up
+    pub fn init_logging(&self) -> anyhow::Result<()> {
+        let file = self
+            .log_file
+            .then(|| {
+                std::fs::create_dir_all(config::data_dir())
+                    .and_then(|_| File::create(config::data_dir().join("game.log")))
+                    .context("failed to open game.log")
+            })
+            .transpose();
+        let (file, error) = match file {
+            Ok(file) => (file, None),
+            Err(err) => (None, Some(err)),
+        };
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+            .with(file.map(|file| {
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file))
+            }))
+            .with(self.log_level)
+            .init();
+        error.map_or(Ok(()), Err)
+    }
+}
+
+impl PlayArgs {
+    pub fn network_mode(&self) -> Option<NetworkMode> {
+        match (self.host, &self.connect) {
+            (Some(port), _) => Some(NetworkMode::Server { port }),
+            (None, Some(address)) => Some(NetworkMode::Client {
+                address: address.clone(),
+            }),
+            (None, None) => None,
+        }
+    }
+
+    // Up to MAX_SPECTATED addresses, the rest are ignored
+    pub fn spectated(&self) -> Vec<String> {
+        self.spectate.iter().take(MAX_SPECTATED).cloned().collect()
+    }
+}
+
+pub fn benchmark(games: usize, difficulty: Difficulty) -> anyhow::Result<()> {
+    let mut config = Config::load().context("failed to load config")?;
+    config.bot.difficulty = difficulty;
+    let result = headless::benchmark(&config, games.max(1), &AtomicUsize::new(0));
+    println!("games:                {}", result.games);
+    println!("decisions per second: {:.0}", result.decisions_per_second);
+    println!("average score:        {:.1}", result.average_score);
+    println!("average lines:        {:.1}", result.average_lines);
+    Ok(())
+}
+
+pub fn validate_replay(path: &Path) -> anyhow::Result<()> {
+    let replay = Replay::load(path)?;
+    // A strict game refused anything against the rules, so finding one means
+    // the replay was made some other way
+    let game = if replay.config.strict_mode {
+        replay.validate()?
+    } else {
+        replay.play_through()
+    };
+    if (game.score, game.lines_cleared) != (replay.score, replay.lines) {
+        anyhow::bail!(
+            "replay ends on {} points and {} lines, but was recorded ending on {} and {}",
+            game.score,
+            game.lines_cleared,
+            replay.score,
+            replay.lines
+        );
+    }
+    println!("ok: {} points, {} lines", game.score, game.lines_cleared);
+    Ok(())
+}
+
+// The locked cells after the bot places `moves` pieces, one row per line with
+// `.` for empty, `#` for garbage and the piece letter otherwise
+pub fn print_board(seed: u64, moves: u32) -> String {
+    let mut driver = HeadlessDriver {
+        game: TetrisGame::seeded(Config::default(), seed),
+        pieces: 0,
+    };
+    driver.game.ai_mode = true;
+    driver.run(moves);
+    let game = &driver.game;
+    let mut text = String::new();
+    for row in &game.board {
+        for cell in row {
+            text += &match cell {
+                CellState::Empty => ".".to_string(),
+                CellState::Garbage => "#".to_string(),
+                CellState::Filled(kind) => format!("{kind:?}"),
+            };
+        }
+        text.push('\n');
+    }
+    text += &format!("score {}, lines {}\n", game.score, game.lines_cleared);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_network_and_log_arguments() {
+        let args = Args::parse_from(["tetris-rs", "--connect", "10.0.0.2:7777"]);
+        assert_eq!(args.log_level, LevelFilter::WARN);
+        let Command::Play(play) = args.command() else {
+            panic!("no subcommand should mean play");
+        };
+        assert!(matches!(
+            play.network_mode(),
+            Some(NetworkMode::Client { address }) if address == "10.0.0.2:7777"
+        ));
+
+        let args = Args::parse_from([
+            "tetris-rs",
+            "play",
+            "--spectate",
+            "a:1",
+            "--spectate",
+            "b:2",
+            "--spectate",
+            "c:3",
+            "--log-level",
+            "debug",
+        ]);
+        assert_eq!(args.log_level, LevelFilter::DEBUG);
+        let Command::Play(play) = args.command() else {
+            panic!("expected play");
+        };
+        assert_eq!(play.spectated(), ["a:1", "b:2"]);
+        assert!(play.network_mode().is_none());
+
+        assert!(Args::try_parse_from(["tetris-rs", "--host", "7777", "--connect", "a:1"]).is_err());
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        let args = Args::parse_from(["tetris-rs", "benchmark", "--games", "10", "--ai", "easy"]);
+        assert!(matches!(
+            args.command(),
+            Command::Benchmark {
+                games: 10,
+                ai: Difficulty::Easy
+            }
+        ));
+        let args = Args::parse_from(["tetris-rs", "print-board", "--moves", "5"]);
+        assert!(matches!(
+            args.command(),
+            Command::PrintBoard { seed: 42, moves: 5 }
+        ));
+        let args = Args::parse_from(["tetris-rs", "validate-replay", "--file", "r.json"]);
+        assert!(matches!(
+            args.command(),
+            Command::ValidateReplay { file } if file == Path::new("r.json")
+        ));
+        // The play options belong to play, not the other commands
+        assert!(Args::try_parse_from(["tetris-rs", "--host", "1", "benchmark"]).is_err());
+    }
+
+    #[test]
+    fn same_seed_prints_the_same_board() {
+        let board = print_board(7, 30);
+        assert_eq!(board, print_board(7, 30));
+        assert_eq!(board.lines().count(), crate::BOARD_HEIGHT + 1);
+        assert!(board.lines().any(|line| line.chars().any(|c| c != '.')));
+    }
+}
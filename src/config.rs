@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::AiBot;
+use crate::bag::Randomizer;
+use crate::gravity::{GravityCurve, SpeedMode};
+use crate::handling::{Handling, HandlingPreset};
+use crate::high_scores::{HighScoreTable, SortBy};
+use crate::keys::KeyBindings;
+use crate::locale::Language;
+use crate::rotation::RotationSystem;
+use crate::scoring::ScoringSystem;
+use crate::theme::{BackgroundPattern, GridStyle, RenderStyle, ThemePreset};
+use crate::TetrominoType;
+
+// Player preferences, kept across restarts and saved on exit
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Cells per second the drawn piece slides sideways; 0 moves it instantly
+    pub slide_speed: f32,
+    // The timings in `handling` came from this preset, unless it's Custom
+    pub handling_preset: HandlingPreset,
+    pub handling: Handling,
+    // Which keys play a one-player game; the local four-player keys are fixed
+    pub key_bindings: KeyBindings,
+    // Flash locked pieces red or green depending on whether they buried holes
+    pub placement_feedback: bool,
+    // Show the hard drop trajectory at all times, not only while a hard drop
+    // waits to be confirmed
+    pub drop_preview: bool,
+    // Name the opening the first pieces build, from openings.toml
+    pub detect_openers: bool,
+    // A lock sound pitched to the piece, in builds with the sound feature
+    pub sound_effects: bool,
+    pub bot: AiBot,
+    // Shown to online opponents
+    pub username: String,
+    // Switch between the dark and light themes along with the system
+    pub adaptive_theme: bool,
+    // Used when the theme doesn't follow the system
+    pub theme: ThemePreset,
+    pub gravity_curve: GravityCurve,
+    // Whether levels or score drive the speed
+    pub speed_mode: SpeedMode,
+    pub scoring: ScoringSystem,
+    pub rotation_system: RotationSystem,
+    // Takes effect from the next game
+    pub randomizer: Randomizer,
+    // Now and then a locked piece starts a short score multiplier
+    pub power_ups: bool,
+    // Refuse inputs the guideline rules don't allow, and check replays for them
+    pub strict_mode: bool,
+    // Announce line clears, level ups and game over for screen readers
+    pub screen_reader: bool,
+    // Pieces, score and the board change instantly instead of animating
+    pub reduced_motion: bool,
+    pub language: Language,
+    pub background_pattern: BackgroundPattern,
+    pub show_grid: bool,
+    // Column and row numbers around the flat board
+    pub show_axis_labels: bool,
+    // Drawn behind the flat board, dimmed; read once at startup
+    pub background_image: Option<PathBuf>,
+    // A dot on the board that flashes with each gravity tick
+    pub show_metronome: bool,
+    pub grid_style: GridStyle,
+    pub manual_drop: bool,
+    pub preview_count: u8,
+    // Each next piece's letter and color name under its preview
+    pub show_piece_labels: bool,
+    pub piece_shadow: bool,
+    pub render_style: RenderStyle,
+    pub show_active_row_highlight: bool,
+    pub confirm_hard_drop: bool,
+    // Pieces the tutorial has already introduced
+    pub shown_tips: HashSet<TetrominoType>,
+    // Finished games, so beginners keep getting tips for a while
+    pub games_played: u32,
+    pub tutorial_completed: bool,
+    // Rows of garbage the board starts with; takes effect from the next game
+    pub starting_garbage: u8,
+    // A blocked spawn tries the two rows above the board before the game ends
+    pub mercy_rule: bool,
+    // Online games are played as king of the hill
+    pub king_of_the_hill: bool,
+    // How many times a second an online game sends its board for spectators
+    pub snapshot_rate: u32,
+    // Best result so far for each end screen stat, by its locale key
+    pub personal_bests: HashMap<String, f32>,
+    pub high_scores: HighScoreTable,
+    // Set when a config that failed to load couldn't be moved aside either, so
+    // exiting doesn't save the defaults over it
+    #[serde(skip)]
+    pub read_only: bool,
+    // The column the high score panel was last sorted on
+    pub high_score_sort: SortBy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            slide_speed: 0.0,
+            handling_preset: HandlingPreset::Guideline,
+            handling: HandlingPreset::Guideline.handling().unwrap(),
+            key_bindings: KeyBindings::default(),
+            placement_feedback: false,
+            drop_preview: false,
+            detect_openers: true,
+            sound_effects: true,
+            bot: AiBot::default(),
+            username: String::new(),
+            adaptive_theme: true,
+            theme: ThemePreset::Dark,
+            gravity_curve: GravityCurve::Linear,
+            speed_mode: SpeedMode::Level,
+            scoring: ScoringSystem::Classic,
+            rotation_system: RotationSystem::Super,
+            randomizer: Randomizer::Random,
+            power_ups: false,
+            strict_mode: false,
+            screen_reader: false,
+            reduced_motion: false,
+            language: Language::English,
+            background_pattern: BackgroundPattern::None,
+            show_grid: true,
+            show_axis_labels: false,
+            background_image: None,
+            show_metronome: false,
+            grid_style: GridStyle::Standard,
+            manual_drop: false,
+            preview_count: 1,
+            piece_shadow: false,
+            show_piece_labels: false,
+            render_style: RenderStyle::Flat,
+            show_active_row_highlight: false,
+            confirm_hard_drop: false,
+            shown_tips: HashSet::new(),
+            games_played: 0,
+            tutorial_completed: false,
+            starting_garbage: 0,
+            mercy_rule: false,
+            king_of_the_hill: false,
+            snapshot_rate: 10,
+            personal_bests: HashMap::new(),
+            high_scores: HighScoreTable::default(),
+            high_score_sort: SortBy::Score,
+            read_only: false,
+        }
+    }
+}
+
+impl Config {
+    // A missing file is a first run and gets the defaults; one that can't be
+    // read or parsed is an error
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config_path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    // Moves a config that failed to load out of the way, so the defaults saved
+    // in its place on exit don't take its high scores with it
+    pub fn back_up_broken() -> anyhow::Result<PathBuf> {
+        let path = config_path();
+        let backup = path.with_extension("toml.bak");
+        fs::rename(&path, &backup)
+            .with_context(|| format!("failed to move {} aside", path.display()))?;
+        Ok(backup)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let text = toml::to_string(self)?;
+        fs::create_dir_all(data_dir()).context("failed to create the data directory")?;
+        fs::write(config_path(), text).context("failed to write the config")
+    }
+}
+
+// Where everything the game writes to disk lives
+pub fn data_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("tetris-rs")
+}
+
+fn config_path() -> PathBuf {
+    data_dir().join("config.toml")
+}
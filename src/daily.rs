@@ -0,0 +1,111 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+// Garbage rows the daily board starts with, the same for everyone that day
+pub const DAILY_GARBAGE: u32 = 4;
+
+// Days since the Unix epoch, which turn over at midnight UTC
+pub fn day_at(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / DAY.as_secs()
+}
+
+pub fn today() -> u64 {
+    day_at(SystemTime::now())
+}
+
+// SplitMix64, written out rather than using std's hasher, which is free to
+// change between Rust versions and would hand players different days
+pub fn seed(day: u64) -> u64 {
+    let mut z = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// The UTC calendar date of `day` as YYYY-MM-DD, by Howard Hinnant's
+// civil_from_days
+pub fn date(day: u64) -> String {
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+// Until the next midnight UTC
+pub fn until_next(time: SystemTime) -> Duration {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Duration::from_secs((day_at(time) + 1) * DAY.as_secs()) - since_epoch
+}
+
+// The best daily challenge score, kept only for the day it was set
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct DailyBest {
+    pub day: u64,
+    pub score: u32,
+}
+
+impl DailyBest {
+    pub fn on(self, day: u64) -> Option<u32> {
+        (self.day == day).then_some(self.score)
+    }
+
+    // True if `score` beat the best for `day`, which then becomes it
+    pub fn record(&mut self, day: u64, score: u32) -> bool {
+        let beaten = self.on(day).is_none_or(|best| score > best);
+        if beaten {
+            *self = DailyBest { day, score };
+        }
+        beaten
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yesterdays_best_is_beaten_by_anything_today() {
+        let mut best = DailyBest::default();
+        assert!(best.record(10, 500));
+        assert!(!best.record(10, 400));
+        assert_eq!(best.on(10), Some(500));
+        assert_eq!(best.on(11), None);
+        assert!(best.record(11, 100));
+        assert_eq!(best.on(11), Some(100));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(20_740), "2026-10-14");
+    }
+
+    #[test]
+    fn days_turn_over_at_midnight_utc() {
+        let midnight = UNIX_EPOCH + DAY * 20_000;
+        let just_before = midnight - Duration::from_secs(1);
+        assert_eq!(day_at(midnight), 20_000);
+        assert_eq!(day_at(just_before), 19_999);
+        assert_eq!(until_next(just_before), Duration::from_secs(1));
+        assert_eq!(until_next(midnight), DAY);
+        assert_eq!(seed(20_000), seed(20_000));
+        assert_ne!(seed(20_000), seed(19_999));
+    }
+}
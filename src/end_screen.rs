@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// The buttons wait this long, so keys mashed as the stack tops out can't restart
+const BUTTON_DELAY: Duration = Duration::from_secs(2);
+const SCORE_COUNT_UP: Duration = Duration::from_secs(1);
+const SCORE_KEY: &str = "score";
+
+// One line of the results: a locale key, the value shown and whether it set a record
+#[derive(Clone)]
+pub struct EndStat {
+    pub label: &'static str,
+    pub value: String,
+    pub new_best: bool,
+}
+
+// What a finished game shows until the next one starts
+#[derive(Clone)]
+pub struct EndScreen {
+    score: u32,
+    pub score_best: bool,
+    pub stats: Vec<EndStat>,
+    // Starts the first time the screen is drawn, after the board has cleared
+    shown_at: Option<Instant>,
+    // Where the game's replay went, once the player saves it
+    pub saved_replay: Option<PathBuf>,
+}
+
+impl EndScreen {
+    // Each stat is (locale key, text, value), where stats with a value count as
+    // records. The records in `bests` are raised to match, unless it is None
+    // because this game shouldn't set any
+    pub fn new(
+        score: u32,
+        stats: Vec<(&'static str, String, Option<f32>)>,
+        mut bests: Option<&mut HashMap<String, f32>>,
+    ) -> Self {
+        let mut beat = |key: &str, value: f32| {
+            let Some(bests) = bests.as_deref_mut() else {
+                return false;
+            };
+            let best = bests.entry(key.to_string()).or_insert(0.0);
+            let new_best = value > *best;
+            *best = best.max(value);
+            new_best
+        };
+        let score_best = beat(SCORE_KEY, score as f32);
+        let stats = stats
+            .into_iter()
+            .map(|(label, value, record)| EndStat {
+                label,
+                value,
+                new_best: record.is_some_and(|record| beat(label, record)),
+            })
+            .collect();
+        EndScreen {
+            score,
+            score_best,
+            stats,
+            shown_at: None,
+            saved_replay: None,
+        }
+    }
+
+    pub fn elapsed(&mut self) -> Duration {
+        self.shown_at.get_or_insert_with(Instant::now).elapsed()
+    }
+
+    // Counts up to the final score over the first second
+    pub fn displayed_score(&mut self, reduced_motion: bool) -> u32 {
+        let progress = self.elapsed().as_secs_f32() / SCORE_COUNT_UP.as_secs_f32();
+        if reduced_motion || progress >= 1.0 {
+            self.score
+        } else {
+            (self.score as f32 * progress) as u32
+        }
+    }
+
+    pub fn buttons_ready(&mut self) -> bool {
+        self.elapsed() >= BUTTON_DELAY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_improvements_are_new_bests() {
+        let mut bests = HashMap::from([(SCORE_KEY.to_string(), 500.0), ("lines".to_string(), 8.0)]);
+        let screen = EndScreen::new(
+            400,
+            vec![
+                ("lines", "12".to_string(), Some(12.0)),
+                ("max_combo", "3".to_string(), Some(3.0)),
+                ("stats_time", "1:00".to_string(), None),
+            ],
+            Some(&mut bests),
+        );
+        assert!(!screen.score_best);
+        let new_bests: Vec<bool> = screen.stats.iter().map(|stat| stat.new_best).collect();
+        assert_eq!(new_bests, [true, true, false]);
+        assert_eq!(bests[SCORE_KEY], 500.0);
+        assert_eq!(bests["lines"], 12.0);
+        assert!(!bests.contains_key("stats_time"));
+
+        // Games that don't count leave the records alone
+        let screen = EndScreen::new(900, Vec::new(), None);
+        assert!(!screen.score_best);
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+const SPAWN_CHANCE: f64 = 0.01;
+const MULTIPLIER_DURATION: Duration = Duration::from_secs(10);
+const MULTIPLIERS: [f32; 3] = [1.5, 2.0, 3.0];
+
+// Scores points times `multiplier` until `expires_after` has passed since `started`
+#[derive(Clone)]
+pub struct ScoreMultiplier {
+    pub expires_after: Duration,
+    pub multiplier: f32,
+    pub started: Instant,
+}
+
+impl ScoreMultiplier {
+    pub fn remaining(&self) -> f32 {
+        1.0 - self.started.elapsed().as_secs_f32() / self.expires_after.as_secs_f32()
+    }
+}
+
+// Random power-ups that turn up as pieces lock
+#[derive(Clone, Default)]
+pub struct EventSystem {
+    pub active: Option<ScoreMultiplier>,
+}
+
+impl EventSystem {
+    // Returns the multiplier when one starts with this piece
+    pub fn on_piece_locked(&mut self) -> Option<ScoreMultiplier> {
+        let mut rng = rand::thread_rng();
+        if self.active.is_some() || !rng.gen_bool(SPAWN_CHANCE) {
+            return None;
+        }
+        let multiplier = ScoreMultiplier {
+            expires_after: MULTIPLIER_DURATION,
+            multiplier: MULTIPLIERS[rng.gen_range(0..MULTIPLIERS.len())],
+            started: Instant::now(),
+        };
+        self.active = Some(multiplier.clone());
+        Some(multiplier)
+    }
+
+    // Wall-clock time, so it runs out even while nothing is being scored
+    pub fn expire(&mut self) {
+        if matches!(&self.active, Some(active) if active.remaining() <= 0.0) {
+            self.active = None;
+        }
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.active.as_ref().map_or(1.0, |active| active.multiplier)
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::config::Config;
+use crate::{CellState, TetrisGame, Tetromino, TetrominoType, BOARD_HEIGHT, BOARD_WIDTH};
+
+// One key press. Holding left or right into the wall is one press too
+#[derive(Clone, Copy)]
+enum Press {
+    Move(i32),
+    ToWall(i32),
+    Rotate,
+    RotateReverse,
+}
+
+const PRESSES: [Press; 6] = [
+    Press::Move(-1),
+    Press::Move(1),
+    Press::ToWall(-1),
+    Press::ToWall(1),
+    Press::Rotate,
+    Press::RotateReverse,
+];
+
+// Works out the fewest presses a placement could have taken, searching from
+// where the piece spawns on an empty board so the stack doesn't get a say.
+// Moves go through a board-less game of its own, so its kicks and rotation
+// system are the player's
+pub struct FinesseAnalyzer {
+    probe: TetrisGame,
+}
+
+impl FinesseAnalyzer {
+    pub fn new(config: &Config) -> Self {
+        let config = Config {
+            reduced_motion: true,
+            starting_garbage: 0,
+            ..config.clone()
+        };
+        let mut probe = TetrisGame::seeded(config, 0);
+        probe.board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+        FinesseAnalyzer { probe }
+    }
+
+    // Breadth first over the pieces one press apart, until one covers the same
+    // cells as `x` and `shape`. None if nothing does, which a placement on the
+    // board never should
+    pub fn optimal_presses(
+        &mut self,
+        kind: TetrominoType,
+        x: usize,
+        shape: &[Vec<bool>],
+    ) -> Option<u32> {
+        let start = Tetromino::new(kind);
+        let mut seen = HashSet::from([(start.x, start.rotation, start.shape.clone())]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((piece, presses)) = queue.pop_front() {
+            if piece.x == x && piece.shape == shape {
+                return Some(presses);
+            }
+            for press in PRESSES {
+                let next = self.press(&piece, press);
+                if seen.insert((next.x, next.rotation, next.shape.clone())) {
+                    queue.push_back((next, presses + 1));
+                }
+            }
+        }
+        None
+    }
+
+    fn press(&mut self, piece: &Tetromino, press: Press) -> Tetromino {
+        let probe = &mut self.probe;
+        probe.current_piece = piece.clone();
+        let primary = probe.config.rotation_system.primary();
+        match press {
+            Press::Move(dx) => {
+                probe.move_piece(dx, 0);
+            }
+            Press::ToWall(dx) => while probe.move_piece(dx, 0) {},
+            Press::Rotate => probe.rotate_piece(primary),
+            Press::RotateReverse => probe.rotate_piece(primary.reversed()),
+        }
+        probe.current_piece.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotated;
+
+    #[test]
+    fn counts_the_fewest_presses() {
+        let config = Config {
+            starting_garbage: 8,
+            ..Config::default()
+        };
+        let mut analyzer = FinesseAnalyzer::new(&config);
+        assert!(analyzer
+            .probe
+            .board
+            .iter()
+            .flatten()
+            .all(|cell| !cell.is_filled()));
+        let t = TetrominoType::T;
+        let flat = t.shape();
+        let spawn_x = Tetromino::new(t).x;
+        assert_eq!(analyzer.optimal_presses(t, spawn_x, &flat), Some(0));
+        assert_eq!(analyzer.optimal_presses(t, spawn_x - 1, &flat), Some(1));
+        // The wall is one press away, and the column beside it one more
+        assert_eq!(analyzer.optimal_presses(t, 0, &flat), Some(1));
+        assert_eq!(analyzer.optimal_presses(t, 1, &flat), Some(2));
+        let upside_down = rotated(&rotated(&flat));
+        assert_eq!(analyzer.optimal_presses(t, spawn_x, &upside_down), Some(2));
+        assert_eq!(analyzer.optimal_presses(t, BOARD_WIDTH, &flat), None);
+    }
+}
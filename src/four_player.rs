@@ -0,0 +1,184 @@
+use eframe::egui;
+use egui::Key;
+use rand::seq::SliceRandom;
+
+use crate::config::Config;
+use crate::locale::locale;
+use crate::spectator::draw_snapshot;
+use crate::theme::Theme;
+use crate::{GameMode, InputEvent, TetrisGame};
+
+const PLAYERS: usize = 4;
+
+// (key, action) for each player, in the order the boards are laid out
+const INPUT_MAPS: [[(Key, InputEvent); 6]; PLAYERS] = [
+    [
+        (Key::A, InputEvent::MoveLeft),
+        (Key::D, InputEvent::MoveRight),
+        (Key::S, InputEvent::SoftDrop),
+        (Key::W, InputEvent::Rotate),
+        (Key::Q, InputEvent::Hold),
+        (Key::E, InputEvent::HardDrop),
+    ],
+    [
+        (Key::ArrowLeft, InputEvent::MoveLeft),
+        (Key::ArrowRight, InputEvent::MoveRight),
+        (Key::ArrowDown, InputEvent::SoftDrop),
+        (Key::ArrowUp, InputEvent::Rotate),
+        (Key::Backspace, InputEvent::Hold),
+        (Key::Enter, InputEvent::HardDrop),
+    ],
+    [
+        (Key::J, InputEvent::MoveLeft),
+        (Key::L, InputEvent::MoveRight),
+        (Key::K, InputEvent::SoftDrop),
+        (Key::I, InputEvent::Rotate),
+        (Key::U, InputEvent::Hold),
+        (Key::O, InputEvent::HardDrop),
+    ],
+    // egui reports the numpad digits as the same keys as the top row
+    [
+        (Key::Num4, InputEvent::MoveLeft),
+        (Key::Num6, InputEvent::MoveRight),
+        (Key::Num5, InputEvent::SoftDrop),
+        (Key::Num8, InputEvent::Rotate),
+        (Key::Num7, InputEvent::Hold),
+        (Key::Num9, InputEvent::HardDrop),
+    ],
+];
+
+// Four boards on one keyboard, each clear sending garbage to a random rival,
+// until one player is left
+#[derive(Clone)]
+pub struct FourPlayerGame {
+    games: [TetrisGame; PLAYERS],
+    alive: [bool; PLAYERS],
+    config: Config,
+    leave: bool,
+}
+
+impl FourPlayerGame {
+    pub fn new(config: Config) -> Self {
+        let game = || {
+            let mut game = TetrisGame::new(config.clone());
+            game.mode = GameMode::Marathon;
+            game.tutorial_step = None;
+            game
+        };
+        FourPlayerGame {
+            games: [game(), game(), game(), game()],
+            alive: [true; PLAYERS],
+            config,
+            leave: false,
+        }
+    }
+
+    pub fn left(&self) -> bool {
+        self.leave
+    }
+
+    // Over once at most one player is left; everyone can top out on the same frame
+    fn finished(&self) -> bool {
+        self.alive.iter().filter(|&&alive| alive).count() <= 1
+    }
+
+    fn step(&mut self, ctx: &egui::Context) {
+        if self.finished() {
+            return;
+        }
+        for (player, game) in self.games.iter_mut().enumerate() {
+            if !self.alive[player] {
+                continue;
+            }
+            for (key, event) in INPUT_MAPS[player] {
+                if ctx.input(|i| i.key_pressed(key)) {
+                    game.apply_input(event);
+                }
+            }
+            game.update();
+        }
+        self.send_garbage();
+        for (alive, game) in self.alive.iter_mut().zip(&self.games) {
+            *alive = !game.game_over;
+        }
+    }
+
+    // Each player's new garbage goes to one rival still in, picked afresh every time
+    fn send_garbage(&mut self) {
+        let mut rng = rand::thread_rng();
+        for player in 0..PLAYERS {
+            let garbage = std::mem::take(&mut self.games[player].outgoing_garbage);
+            if garbage == 0 {
+                continue;
+            }
+            let rivals: Vec<usize> = (0..PLAYERS)
+                .filter(|&rival| rival != player && self.alive[rival])
+                .collect();
+            if let Some(&rival) = rivals.choose(&mut rng) {
+                self.games[rival].pending_garbage += garbage as u32;
+            }
+        }
+    }
+}
+
+impl eframe::App for FourPlayerGame {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.step(ctx);
+        let t = locale(self.config.language);
+        let theme = if self.config.adaptive_theme {
+            Theme::system_adaptive(ctx)
+        } else {
+            self.config.theme.theme()
+        };
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(t.get("four_player"));
+                if ui.button(t.get("back")).clicked() {
+                    self.leave = true;
+                }
+            });
+            if self.finished() {
+                match self.alive.iter().position(|&alive| alive) {
+                    Some(winner) => ui.heading(t.fill("player_wins", &[&(winner + 1)])),
+                    None => ui.heading(t.get("draw")),
+                };
+                if ui.button(t.get("play_again")).clicked() {
+                    *self = FourPlayerGame::new(self.config.clone());
+                }
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for row in [[0, 1], [2, 3]] {
+                    ui.horizontal(|ui| {
+                        for player in row {
+                            let mut snapshot = self.games[player].snapshot();
+                            snapshot.username = format!("P{}", player + 1);
+                            draw_snapshot(ui, &snapshot, &theme, self.config.language);
+                            ui.add_space(16.0);
+                        }
+                    });
+                }
+            });
+        });
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn garbage_only_goes_to_players_still_in() {
+        let mut game = FourPlayerGame::new(Config::default());
+        game.alive = [true, false, true, false];
+        game.games[0].outgoing_garbage = 3;
+        game.games[1].outgoing_garbage = 2;
+        game.send_garbage();
+        let pending = game.games.each_ref().map(|game| game.pending_garbage);
+        // P3 is P1's only rival left, and P2's garbage lands on P1 or P3
+        assert_eq!(pending[2], 3 + if pending[0] == 2 { 0 } else { 2 });
+        assert_eq!(pending[1] + pending[3], 0);
+        assert_eq!(pending.iter().sum::<u32>(), 5);
+        assert!(game.games.iter().all(|game| game.outgoing_garbage == 0));
+    }
+}
@@ -0,0 +1,2893 @@
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use eframe::egui;
+use egui::Color32;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::bag::{PieceBag, Randomizer};
+use crate::challenge::ChallengeConfig;
+use crate::config::Config;
+use crate::end_screen::EndScreen;
+use crate::events::EventSystem;
+use crate::finesse::FinesseAnalyzer;
+use crate::four_player::FourPlayerGame;
+use crate::garbage::ClearType;
+use crate::gravity::{gravity_interval, SpeedMode};
+use crate::handling::HeldKey;
+use crate::headless::BenchmarkResult;
+use crate::high_scores::HighScoreEntry;
+use crate::lobby::Lobby;
+use crate::locale::{locale, Locale};
+use crate::net::{NetworkLink, Packet};
+use crate::openers::{OpenerDetector, OPENER_PIECES};
+use crate::replay::{Replay, ReplayEvent};
+use crate::rotation::RotationDir;
+#[cfg(feature = "sound")]
+use crate::sound::SoundEngine;
+use crate::spectator::BoardSnapshot;
+use crate::theme::Theme;
+use crate::tutorial::{TutorialStep, TutorialSystem};
+use crate::{config, daily, garbage, gravity, handling, headless, strict, tutorial};
+
+pub(crate) const BOARD_WIDTH: usize = 10;
+pub(crate) const BOARD_HEIGHT: usize = 20;
+const ROTATION_ANIM_DURATION: Duration = Duration::from_millis(80);
+pub(crate) const LINES_PER_LEVEL: u32 = 10;
+pub(crate) const LPM_WINDOW: Duration = Duration::from_secs(60);
+const APM_WINDOW: Duration = Duration::from_secs(60);
+// Most moves kept for the APM, which caps it at this many
+const APM_HISTORY: usize = 600;
+// Seconds of history each point of the lines-per-minute graph averages over
+const LPM_SMOOTHING: usize = 10;
+pub(crate) const GLOW_DURATION: Duration = Duration::from_millis(200);
+const LOCK_FLASH_DURATION: Duration = Duration::from_millis(50);
+pub(crate) const TRAIL_DURATION: Duration = Duration::from_millis(200);
+const SHAKE_FROM: f32 = 0.75;
+const SHAKE_FULL: f32 = 0.95;
+pub(crate) const SHAKE_MAX_AMPLITUDE: f32 = 3.0;
+pub(crate) const KING_OF_THE_HILL_POWER: f32 = 1.5;
+// Hard drops shorter than this leave no trail
+const TRAIL_MIN_ROWS: usize = 4;
+// Gravity at least this fast leaves a trail a row at a time
+const TRAIL_GRAVITY_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_GLOW_CELLS: usize = 4;
+// How long the bot waits between placements when playing in the window
+const BOT_MOVE_INTERVAL: Duration = Duration::from_millis(150);
+pub(crate) const LOCK_DELAY: Duration = Duration::from_millis(500);
+// How long a new piece takes to slide in from above the board
+const SPAWN_FALL_IN: Duration = Duration::from_millis(80);
+const MAX_LOCK_RESETS: u32 = 15;
+pub(crate) const MAX_SPINS_IN_PLACE: u32 = 3;
+const MOVE_LOG_CAPACITY: usize = 200;
+pub(crate) const MAX_STARTING_GARBAGE: u8 = 10;
+// Gravity faster than this beats the metronome every few ticks instead of every one
+const METRONOME_MIN_BEAT: Duration = Duration::from_millis(100);
+// Where a blocked spawn is tried next, in the rows above the board
+const MERCY_ROWS: [isize; 2] = [-1, -2];
+pub(crate) const BENCHMARK_GAMES: usize = 100;
+pub(crate) const REWIND_TOKENS: u8 = 3;
+const REWIND_INTERVAL: Duration = Duration::from_millis(500);
+// Ten points half a second apart, so a rewind goes back five seconds
+const REWIND_POINTS: usize = 10;
+pub(crate) const SCORE_POPUP_DURATION: Duration = Duration::from_secs(1);
+pub(crate) const PLACEMENT_FEEDBACK_DURATION: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) enum TetrominoType {
+    I,
+    J,
+    L,
+    O,
+    S,
+    T,
+    Z,
+}
+
+impl TetrominoType {
+    pub(crate) const ALL: [TetrominoType; 7] = [
+        TetrominoType::I,
+        TetrominoType::J,
+        TetrominoType::L,
+        TetrominoType::O,
+        TetrominoType::S,
+        TetrominoType::T,
+        TetrominoType::Z,
+    ];
+
+    pub(crate) fn shape(self) -> Vec<Vec<bool>> {
+        match self {
+            TetrominoType::I => vec![
+                vec![true, true, true, true],
+                vec![false, false, false, false],
+            ],
+            TetrominoType::J => vec![vec![true, false, false], vec![true, true, true]],
+            TetrominoType::L => vec![vec![false, false, true], vec![true, true, true]],
+            TetrominoType::O => vec![vec![true, true], vec![true, true]],
+            TetrominoType::S => vec![vec![false, true, true], vec![true, true, false]],
+            TetrominoType::T => vec![vec![false, true, false], vec![true, true, true]],
+            TetrominoType::Z => vec![vec![true, true, false], vec![false, true, true]],
+        }
+    }
+}
+
+// What a board cell holds; the theme decides what color that is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum CellState {
+    #[default]
+    Empty,
+    Filled(TetrominoType),
+    // Rows pushed up from below by an opponent
+    Garbage,
+}
+
+impl CellState {
+    pub(crate) fn is_filled(self) -> bool {
+        self != CellState::Empty
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Tetromino {
+    pub(crate) kind: TetrominoType,
+    pub(crate) shape: Vec<Vec<bool>>,
+    pub(crate) x: usize,
+    // Negative while the piece pokes out above the board, where only a mercy
+    // spawn puts it
+    pub(crate) y: isize,
+    // Quarter turns clockwise from the spawn orientation
+    pub(crate) rotation: u8,
+    pub(crate) visual_x: f32,
+    pub(crate) visual_y: f32,
+    // (start angle, end angle, start time) of the tween after a rotation
+    rotation_anim: Option<(f32, f32, Instant)>,
+    // Whether the last thing that moved it was a rotation, which a T-spin needs
+    spun: bool,
+}
+
+impl Tetromino {
+    pub(crate) fn new(kind: TetrominoType) -> Self {
+        let shape = kind.shape();
+        // Centered, leaning left when the gap can't be split evenly
+        let x = (BOARD_WIDTH - shape[0].len()) / 2;
+        Tetromino {
+            kind,
+            shape,
+            x,
+            y: 0,
+            rotation: 0,
+            visual_x: x as f32,
+            visual_y: 0.0,
+            rotation_anim: None,
+            spun: false,
+        }
+    }
+
+    // The average of the filled cells' centers, in cells from the shape's top left
+    pub(crate) fn center_of_mass(&self) -> egui::Vec2 {
+        let cells: Vec<egui::Vec2> = self
+            .shape
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|&(_, &cell)| cell)
+                    .map(move |(x, _)| egui::vec2(x as f32 + 0.5, y as f32 + 0.5))
+            })
+            .collect();
+        cells.iter().fold(egui::Vec2::ZERO, |sum, &cell| sum + cell) / cells.len() as f32
+    }
+
+    // What a turn pivots on, in the same units: the middle of the shape's box.
+    // The turned shape keeps its top left where it was, and kicks shift it from there
+    pub(crate) fn rotation_center(&self) -> egui::Vec2 {
+        egui::vec2(self.shape[0].len() as f32, self.shape.len() as f32) / 2.0
+    }
+
+    // Still sliding in after spawning. The player's keys wait for it to arrive
+    pub(crate) fn entering(&self) -> bool {
+        self.visual_y < 0.0
+    }
+
+    pub(crate) fn rotation_angle(&self) -> Option<f32> {
+        let (start, end, started) = self.rotation_anim?;
+        let t = started.elapsed().as_secs_f32() / ROTATION_ANIM_DURATION.as_secs_f32();
+        (t < 1.0).then_some(start + (end - start) * t)
+    }
+}
+
+// Tint over freshly locked cells: red if they buried holes, green if they helped
+#[derive(Clone)]
+pub(crate) struct PlacementFeedback {
+    pub(crate) cells: Vec<(usize, usize)>,
+    pub(crate) color: Color32,
+    pub(crate) born: Instant,
+}
+
+// A label floating up from the board after a clear. Several can fire from a
+// single lock, so each one starts a little lower and rises a little slower.
+#[derive(Clone)]
+pub(crate) struct ScorePopup {
+    pub(crate) text: String,
+    // Higher-value labels are painted on top
+    value: u32,
+    pub(crate) offset: f32,
+    pub(crate) rise_speed: f32,
+    pub(crate) born: Instant,
+}
+
+// Wipes the board row by row from the bottom once the game ends
+#[derive(Clone)]
+pub(crate) struct GameOverClearAnim {
+    // Row flashing now, -1 once the board is empty
+    pub(crate) current_row: i32,
+    pub(crate) last_clear: Instant,
+}
+
+// A freshly locked cell that briefly glows white
+#[derive(Clone)]
+pub(crate) struct GlowCell {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) born: Instant,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct GameStats {
+    total_cells_placed: u32,
+    // Cells that ended up as holes buried under other pieces
+    total_cells_wasted: u32,
+    // Unpaused time since the game started or the stats were reset
+    pub(crate) play_time: Duration,
+    pub(crate) pieces: u32,
+    lines: u32,
+    max_combo: u32,
+    t_spins: u32,
+    perfect_clears: u32,
+    // Indexed in TetrominoType::ALL order
+    piece_counts: [u32; 7],
+    // Moves, rotations and rows hard dropped, but not rows fallen on their own
+    moves: u32,
+    // Play time at each of the latest moves, so the paused time doesn't count
+    move_times: VecDeque<Duration>,
+    // Presses beyond the fewest each placement could have taken
+    pub(crate) finesse_errors: u32,
+}
+
+impl GameStats {
+    pub(crate) fn efficiency(&self) -> f32 {
+        if self.total_cells_placed == 0 {
+            return 1.0;
+        }
+        1.0 - self.total_cells_wasted as f32 / self.total_cells_placed as f32
+    }
+
+    fn record_moves(&mut self, count: u32) {
+        self.moves += count;
+        for _ in 0..count {
+            self.move_times.push_back(self.play_time);
+        }
+        while self.move_times.len() > APM_HISTORY {
+            self.move_times.pop_front();
+        }
+    }
+
+    // Moves over the last minute of play
+    fn actions_per_minute(&self) -> f32 {
+        let since = self.play_time.saturating_sub(APM_WINDOW);
+        self.move_times.iter().filter(|&&at| at >= since).count() as f32
+    }
+
+    fn pieces_per_second(&self) -> f32 {
+        self.pieces as f32 / self.play_time.as_secs_f32().max(1.0)
+    }
+
+    fn lines_per_minute(&self) -> f32 {
+        self.lines as f32 * 60.0 / self.play_time.as_secs_f32().max(1.0)
+    }
+
+    pub(crate) fn summary(&self, t: &Locale) -> String {
+        let pieces = TetrominoType::ALL
+            .iter()
+            .zip(self.piece_counts)
+            .map(|(kind, count)| format!("{:?}: {}", kind, count))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!(
+            "{}: {}:{:02}\n\
+             {}: {}\n\
+             {}: {}\n\
+             {}: {:.0}%\n\
+             {}: {}\n\
+             {}: {}\n\
+             {}: {}\n\
+             {}: {}\n\
+             {}: {:.0}\n\
+             {}: {:.2}\n\
+             {}: {:.1}\n\
+             {}: {}\n\
+             {}",
+            t.get("stats_time"),
+            self.play_time.as_secs() / 60,
+            self.play_time.as_secs() % 60,
+            t.get("stats_pieces"),
+            self.pieces,
+            t.get("lines"),
+            self.lines,
+            t.get("stats_efficiency"),
+            self.efficiency() * 100.0,
+            t.get("max_combo"),
+            self.max_combo,
+            t.get("stats_t_spins"),
+            self.t_spins,
+            t.get("perfect_clear"),
+            self.perfect_clears,
+            t.get("stats_moves"),
+            self.moves,
+            t.get("actions_per_minute"),
+            self.actions_per_minute(),
+            t.get("pieces_per_second"),
+            self.pieces_per_second(),
+            t.get("lines_per_minute"),
+            self.lines_per_minute(),
+            t.get("column_finesse"),
+            self.finesse_errors,
+            pieces,
+        )
+    }
+}
+
+// Everything that can move the current piece, whether from a player or the bot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum InputEvent {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    Rotate,
+    RotateReverse,
+    Hold,
+    // The piece fell a row on its own
+    Gravity,
+    // The piece sat on the stack for the whole lock delay
+    Lock,
+}
+
+impl InputEvent {
+    pub(crate) const ALL: [InputEvent; 9] = [
+        InputEvent::MoveLeft,
+        InputEvent::MoveRight,
+        InputEvent::SoftDrop,
+        InputEvent::HardDrop,
+        InputEvent::Rotate,
+        InputEvent::RotateReverse,
+        InputEvent::Hold,
+        InputEvent::Gravity,
+        InputEvent::Lock,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GameMode {
+    Marathon,
+    // Pieces only move down when the player drops them, and the level stays at 1
+    Manual,
+    // Introduces each piece the first time it comes up
+    Tutorial,
+    // The bot plays headless games in the background and reports how it did
+    Benchmark,
+    // Online, with the lower stack's clears hitting harder
+    KingOfTheHill,
+    // The same pieces and starting board for everyone on the same UTC day
+    DailyChallenge,
+    // No gravity or lock delay: a piece that comes to rest waits there until
+    // Enter places it, so there's all the time in the world to think
+    TrainingWheels,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Crown {
+    Ours,
+    Theirs,
+}
+
+// The parts of the game a rewind puts back, taken every REWIND_INTERVAL of play
+#[derive(Clone)]
+struct RewindPoint {
+    play_time: Duration,
+    board: Vec<Vec<CellState>>,
+    current_piece: Tetromino,
+    held_piece: Option<TetrominoType>,
+    can_hold: bool,
+    bag: PieceBag,
+    score: u32,
+    level: u32,
+    lines_cleared: u32,
+    combo: u32,
+    back_to_back: bool,
+    update_interval: Duration,
+    pieces_used: u32,
+    // How many inputs the replay had, so rewound ones drop out of it
+    replay_events: usize,
+}
+
+// Shared with the worker thread, which fills in `result` when it is done
+#[derive(Clone, Default)]
+pub(crate) struct BenchmarkRun {
+    pub(crate) finished_games: Arc<AtomicUsize>,
+    pub(crate) result: Arc<Mutex<Option<BenchmarkResult>>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct TetrisGame {
+    pub(crate) config: Config,
+    pub(crate) theme: Theme,
+    pub(crate) mode: GameMode,
+    pub(crate) board: Vec<Vec<CellState>>,
+    pub(crate) current_piece: Tetromino,
+    pub(crate) bag: PieceBag,
+    pub(crate) held_piece: Option<TetrominoType>,
+    // Cleared by a hold until the next piece spawns
+    pub(crate) can_hold: bool,
+    // Rotate and hold keys that were down when the current frame began, applied
+    // to the next piece the moment it spawns
+    pub(crate) queued_rotation: Option<RotationDir>,
+    pub(crate) queued_hold: bool,
+    // Set once the press of a rotate or hold key still down has acted, on a
+    // piece or as one spawned, so it doesn't act again until it's let go
+    pub(crate) rotation_key_spent: bool,
+    pub(crate) hold_key_spent: bool,
+    pub(crate) game_over: bool,
+    pub(crate) score: u32,
+    // Counts up toward `score` each frame so bonuses visibly tick in
+    pub(crate) displayed_score: f32,
+    pub(crate) level: u32,
+    pub(crate) lines_cleared: u32,
+    pub(crate) level_up_at: Option<Instant>,
+    // The opening the first pieces built, and when it was recognised
+    pub(crate) opener: Option<(&'static str, Instant)>,
+    // None without an audio device, and in headless runs
+    #[cfg(feature = "sound")]
+    pub(crate) sound: Option<Arc<SoundEngine>>,
+    // The piece that locked since the last frame, for the frame to play. A
+    // clone that locks one in the bot's lookahead is dropped before it's heard
+    #[cfg(feature = "sound")]
+    pub(crate) lock_sound: Option<TetrominoType>,
+    pub(crate) placement_feedbacks: Vec<PlacementFeedback>,
+    pub(crate) stats: GameStats,
+    // (sample time, lines cleared so far), one sample per second
+    lpm_history: VecDeque<(Instant, u32)>,
+    // Consecutive locks that cleared at least one line
+    combo: u32,
+    // Whether the last clear was a Tetris or a T-spin
+    back_to_back: bool,
+    // Set as a T-spin locks, for the clear it makes to be scored as one
+    t_spin: bool,
+    // Rows earned against the opponent since they were last sent
+    pub(crate) outgoing_garbage: u8,
+    // Rows the opponent sent that haven't risen yet; attacks cancel them before any
+    // rows go out
+    pub(crate) pending_garbage: u32,
+    pub(crate) score_popups: Vec<ScorePopup>,
+    pub(crate) glow_cells: Vec<GlowCell>,
+    // Cells a fast piece fell through, fading out behind it
+    pub(crate) trail: Vec<(usize, usize, Instant)>,
+    // The whole last piece, drawn brighter for a moment so the lock is felt
+    pub(crate) locked_cells_flash: Option<(Vec<(usize, usize)>, Instant)>,
+    pub(crate) debug_overlay: bool,
+    // Draws the board as raised blocks on an isometric floor
+    pub(crate) isometric: bool,
+    // Outlines every spot the current piece could land, for practice
+    pub(crate) placement_preview: bool,
+    pub(crate) show_stats: bool,
+    pub(crate) show_controls: bool,
+    // When the controls overlay closes by itself, if it was opened for the player
+    pub(crate) controls_close_at: Option<Instant>,
+    started_at: Instant,
+    // Each entry's event, so the panel can filter on it, and its line of text
+    pub(crate) move_log: VecDeque<(InputEvent, String)>,
+    pub(crate) show_move_log: bool,
+    // Actions left out of the move log panel
+    pub(crate) move_log_hidden: Vec<InputEvent>,
+    pub(crate) game_over_anim: Option<GameOverClearAnim>,
+    // The UTC day a daily challenge is for, even once midnight has passed
+    pub(crate) daily_day: u64,
+    // Rewinds left this game, and where they can go back to, oldest first
+    pub(crate) rewind_tokens: u8,
+    rewind_history: VecDeque<RewindPoint>,
+    pub(crate) end_screen: Option<EndScreen>,
+    pub(crate) events: EventSystem,
+    // Spoken by the screen reader on the next frame
+    pub(crate) announcements: Vec<String>,
+    pub(crate) challenge: Option<ChallengeConfig>,
+    pub(crate) pieces_used: u32,
+    // When hard drop was first pressed, if the drop is waiting to be confirmed
+    pub(crate) hard_drop_pending: Option<Instant>,
+    pub(crate) tutorial: TutorialSystem,
+    // Where the first-game walkthrough is up to, until it is done
+    pub(crate) tutorial_step: Option<TutorialStep>,
+    // Let the bot from `config.bot` play instead of the keyboard
+    pub(crate) ai_mode: bool,
+    last_bot_move: Instant,
+    pub(crate) benchmark: Option<BenchmarkRun>,
+    // Connection to an online opponent, which garbage is traded with
+    pub(crate) network: Option<Arc<NetworkLink>>,
+    // When the board was last sent to the opponent and the host's spectators
+    last_snapshot: Instant,
+    // Set once the board showing the game over has gone out, after which
+    // there's nothing new to send
+    final_snapshot_sent: bool,
+    // The opponent's latest board
+    pub(crate) opponent: Option<BoardSnapshot>,
+    // Things that went wrong without stopping the game, until dismissed
+    pub(crate) warnings: Vec<String>,
+    // Config::background_image, loaded once
+    pub(crate) background_image: Option<egui::TextureHandle>,
+    // Filled in by the thread behind the save dialog once the CSV is written,
+    // and left None if the dialog is cancelled
+    pub(crate) scores_export: Arc<Mutex<Option<anyhow::Result<std::path::PathBuf>>>>,
+    pub(crate) scores_exported: Option<std::path::PathBuf>,
+    // Every input so far, shared with the bot's clones rather than copied into each
+    pub(crate) replay: Arc<Mutex<Replay>>,
+    // Built at the first lock it judges and kept for the rest of the game,
+    // since the analyzer is a game of its own and can't be made alongside this one
+    finesse: Arc<Mutex<Option<FinesseAnalyzer>>>,
+    // Shown instead of the board while looking for an opponent
+    pub(crate) lobby: Option<Lobby>,
+    // Shown instead of the board during a local four-player game
+    pub(crate) four_player: Option<Box<FourPlayerGame>>,
+    pub(crate) paused: bool,
+    pub(crate) fullscreen: bool,
+    // Restored when leaving fullscreen
+    pub(crate) windowed_size: Option<egui::Vec2>,
+    // Last title handed to the window, so it is only updated when it changes
+    pub(crate) window_title: String,
+    last_update: Instant,
+    // Counts the gravity ticks, for the metronome
+    gravity_ticks: u32,
+    // Keys the player has pressed for the current piece, for the finesse count
+    pub(crate) finesse_presses: u32,
+    // Left or right, while held
+    pub(crate) shift: Option<(InputEvent, HeldKey)>,
+    pub(crate) soft_drop: Option<HeldKey>,
+    // When the piece came to rest on the stack, if it's resting
+    pub(crate) lock_timer: Option<Instant>,
+    // The lowest row the piece has reached, and the lock resets used and spins
+    // made there
+    pub(crate) lowest_y: isize,
+    lock_resets: u32,
+    pub(crate) spins_in_place: u32,
+    pub(crate) update_interval: Duration,
+}
+
+impl TetrisGame {
+    pub(crate) fn new(config: Config) -> Self {
+        TetrisGame::seeded(config, rand::random())
+    }
+
+    // The same seed always deals the same pieces
+    pub(crate) fn seeded(config: Config, seed: u64) -> Self {
+        let mut bag = PieceBag::seeded(config.randomizer, seed);
+        let mut game = TetrisGame {
+            replay: Arc::new(Mutex::new(Replay::new(config.clone(), seed))),
+            theme: config.theme.theme(),
+            update_interval: gravity_interval(1, config.gravity_curve),
+            tutorial_step: (!config.tutorial_completed).then_some(TutorialStep::Move),
+            mode: if config.manual_drop {
+                GameMode::Manual
+            } else {
+                GameMode::Marathon
+            },
+            config,
+            board: vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT],
+            current_piece: Tetromino::new(bag.next()),
+            held_piece: None,
+            can_hold: true,
+            queued_rotation: None,
+            queued_hold: false,
+            rotation_key_spent: false,
+            hold_key_spent: false,
+            bag,
+            game_over: false,
+            score: 0,
+            displayed_score: 0.0,
+            level: 1,
+            lines_cleared: 0,
+            level_up_at: None,
+            opener: None,
+            #[cfg(feature = "sound")]
+            sound: None,
+            #[cfg(feature = "sound")]
+            lock_sound: None,
+            placement_feedbacks: Vec::new(),
+            stats: GameStats::default(),
+            lpm_history: VecDeque::new(),
+            combo: 0,
+            back_to_back: false,
+            t_spin: false,
+            outgoing_garbage: 0,
+            pending_garbage: 0,
+            score_popups: Vec::new(),
+            glow_cells: Vec::new(),
+            locked_cells_flash: None,
+            trail: Vec::new(),
+            debug_overlay: false,
+            isometric: false,
+            placement_preview: false,
+            show_stats: false,
+            show_controls: false,
+            controls_close_at: None,
+            started_at: Instant::now(),
+            move_log: VecDeque::new(),
+            show_move_log: false,
+            move_log_hidden: vec![InputEvent::Gravity],
+            game_over_anim: None,
+            daily_day: 0,
+            rewind_tokens: REWIND_TOKENS,
+            rewind_history: VecDeque::new(),
+            end_screen: None,
+            events: EventSystem::default(),
+            announcements: Vec::new(),
+            challenge: None,
+            pieces_used: 0,
+            hard_drop_pending: None,
+            tutorial: TutorialSystem::default(),
+            ai_mode: false,
+            last_bot_move: Instant::now(),
+            benchmark: None,
+            network: None,
+            last_snapshot: Instant::now(),
+            final_snapshot_sent: false,
+            opponent: None,
+            warnings: Vec::new(),
+            background_image: None,
+            scores_export: Arc::new(Mutex::new(None)),
+            scores_exported: None,
+            finesse: Arc::new(Mutex::new(None)),
+            lobby: None,
+            four_player: None,
+            paused: false,
+            fullscreen: false,
+            windowed_size: None,
+            window_title: String::new(),
+            last_update: Instant::now(),
+            gravity_ticks: 0,
+            finesse_presses: 0,
+            shift: None,
+            soft_drop: None,
+            lock_timer: None,
+            lowest_y: 0,
+            lock_resets: 0,
+            spins_in_place: 0,
+        };
+        let garbage = game.config.starting_garbage.min(MAX_STARTING_GARBAGE);
+        game.inject_garbage(garbage.into());
+        game.replay.lock().unwrap().board = game.board.clone();
+        game.spawn_piece();
+        game
+    }
+
+    // Seeded by the day, with the 7-bag whatever the player's randomizer, so
+    // everyone gets the same game
+    fn daily(config: Config, day: u64) -> Self {
+        let seed = daily::seed(day);
+        let mut game = TetrisGame::seeded(config, seed);
+        game.mode = GameMode::DailyChallenge;
+        game.daily_day = day;
+        game.tutorial_step = None;
+        game.bag = PieceBag::seeded(Randomizer::SevenBag, seed);
+        game.board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+        game.inject_garbage_from(daily::DAILY_GARBAGE, &mut StdRng::seed_from_u64(seed));
+        game.current_piece = Tetromino::new(game.bag.next());
+        let mut replay = game.replay.lock().unwrap();
+        replay.config.randomizer = Randomizer::SevenBag;
+        replay.board = game.board.clone();
+        drop(replay);
+        game
+    }
+
+    pub(crate) fn as_challenge(config: Config, challenge: ChallengeConfig) -> Self {
+        TetrisGame::new(config).with_challenge(challenge)
+    }
+
+    pub(crate) fn with_challenge(mut self, challenge: ChallengeConfig) -> Self {
+        if let Some(board) = &challenge.starting_board {
+            self.board = board.clone();
+        }
+        let mut replay = self.replay.lock().unwrap();
+        replay.board = self.board.clone();
+        replay.challenge = Some(challenge.clone());
+        drop(replay);
+        self.challenge = Some(challenge);
+        self.tutorial_step = None;
+        self
+    }
+
+    // Starts over with the same settings, opponent, challenge and tutorial. The
+    // window stays as it was, and so do the bot and the debug overlay
+    pub(crate) fn restart(&mut self) {
+        let network = self.network.take();
+        let warnings = std::mem::take(&mut self.warnings);
+        let background_image = self.background_image.take();
+        #[cfg(feature = "sound")]
+        let sound = self.sound.take();
+        // An export still being written reports back after the restart
+        let scores_export = (self.scores_export.clone(), self.scores_exported.take());
+        let window = (
+            self.fullscreen,
+            self.windowed_size.take(),
+            std::mem::take(&mut self.window_title),
+        );
+        let (ai_mode, debug_overlay) = (self.ai_mode, self.debug_overlay);
+        let tutorial = self.mode == GameMode::Tutorial;
+        let daily = self.mode == GameMode::DailyChallenge;
+        let training_wheels = self.mode == GameMode::TrainingWheels;
+        *self = match self.challenge.take() {
+            Some(challenge) => TetrisGame::as_challenge(self.config.clone(), challenge),
+            None if daily => TetrisGame::daily(self.config.clone(), daily::today()),
+            None => TetrisGame::new(self.config.clone()),
+        };
+        self.network = network;
+        self.warnings = warnings;
+        self.background_image = background_image;
+        #[cfg(feature = "sound")]
+        {
+            self.sound = sound;
+        }
+        (self.scores_export, self.scores_exported) = scores_export;
+        (self.fullscreen, self.windowed_size, self.window_title) = window;
+        (self.ai_mode, self.debug_overlay) = (ai_mode, debug_overlay);
+        if self.network.is_some() && self.config.king_of_the_hill {
+            self.mode = GameMode::KingOfTheHill;
+        }
+        if training_wheels {
+            self.mode = GameMode::TrainingWheels;
+        }
+        if tutorial {
+            self.mode = GameMode::Tutorial;
+            // The first piece spawned before the mode was set
+            self.tutorial
+                .on_spawn(self.current_piece.kind, &mut self.config.shown_tips);
+        }
+    }
+
+    fn tips_enabled(&self) -> bool {
+        match self.mode {
+            GameMode::Tutorial => true,
+            GameMode::Marathon => {
+                self.challenge.is_none()
+                    && !self.ai_mode
+                    && self.config.games_played < tutorial::BEGINNER_GAMES
+            }
+            GameMode::Manual
+            | GameMode::Benchmark
+            | GameMode::KingOfTheHill
+            | GameMode::DailyChallenge
+            | GameMode::TrainingWheels => false,
+        }
+    }
+
+    // Pieces only fall when the player moves them
+    pub(crate) fn has_gravity(&self) -> bool {
+        !matches!(self.mode, GameMode::Manual | GameMode::TrainingWheels)
+    }
+
+    // In training wheels, a piece that can't go any lower is waiting for Enter
+    pub(crate) fn awaiting_placement(&self) -> bool {
+        let piece = &self.current_piece;
+        self.mode == GameMode::TrainingWheels
+            && !self.game_over
+            && self.shape_collides(&piece.shape, piece.x, piece.y + 1)
+    }
+
+    fn spawn_piece(&mut self) {
+        if !self.game_over {
+            self.current_piece = Tetromino::new(self.bag.next());
+            tracing::debug!("piece spawned: {:?}", self.current_piece.kind);
+            self.can_hold = true;
+            self.finesse_presses = 0;
+            self.reset_lock_delay();
+            self.hard_drop_pending = None;
+            if self.tips_enabled() {
+                self.tutorial
+                    .on_spawn(self.current_piece.kind, &mut self.config.shown_tips);
+            }
+            // Keys held as the piece appears act on it before it is checked for fit
+            if std::mem::take(&mut self.queued_hold) {
+                self.hold_key_spent = true;
+                self.hold_piece();
+            }
+            if let Some(dir) = self.queued_rotation.take() {
+                self.rotation_key_spent = true;
+                self.rotate_piece(dir);
+            }
+            if self.piece_collides() && self.config.mercy_rule {
+                self.try_mercy_spawn();
+            }
+            if self.piece_collides() {
+                self.game_over = true;
+            } else if !self.config.reduced_motion {
+                // Drawn from just above the board, sliding in over SPAWN_FALL_IN
+                self.current_piece.visual_y = -(self.current_piece.shape.len() as f32);
+            }
+        }
+        self.assert_board_valid();
+    }
+
+    // The rotate and hold keys down this frame, queued for the next spawn unless
+    // their press has already acted
+    pub(crate) fn queue_spawn_keys(&mut self, rotation: Option<RotationDir>, hold: bool) {
+        self.rotation_key_spent &= rotation.is_some();
+        self.hold_key_spent &= hold;
+        self.queued_rotation = rotation.filter(|_| !self.rotation_key_spent);
+        self.queued_hold = hold && !self.hold_key_spent;
+    }
+
+    // Raises a blocked spawn a row at a time, leaving it where it was if even
+    // the highest row is blocked
+    fn try_mercy_spawn(&mut self) {
+        let spawn_y = self.current_piece.y;
+        for y in MERCY_ROWS {
+            self.current_piece.y = y;
+            if !self.piece_collides() {
+                self.lowest_y = y;
+                return;
+            }
+        }
+        self.current_piece.y = spawn_y;
+    }
+
+    // Once per piece: swaps the current piece with the held one, or with the
+    // next piece when nothing is held yet
+    fn hold_piece(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        let kind = self.current_piece.kind;
+        self.current_piece = match self.held_piece.replace(kind) {
+            Some(held) => Tetromino::new(held),
+            None => Tetromino::new(self.bag.next()),
+        };
+        self.can_hold = false;
+        self.finesse_presses = 0;
+        self.reset_lock_delay();
+    }
+
+    // A new piece gets the whole lock delay and all of its resets
+    fn reset_lock_delay(&mut self) {
+        self.lock_timer = None;
+        self.lowest_y = self.current_piece.y;
+        self.lock_resets = 0;
+        self.spins_in_place = 0;
+    }
+
+    // Moving or rotating a resting piece restarts its lock delay, up to
+    // MAX_LOCK_RESETS times per row it reaches. Spinning more than
+    // MAX_SPINS_IN_PLACE times without getting any lower ends the resets for
+    // the piece, so it can't be kept up forever
+    fn extend_lock_delay(&mut self, rotation: bool) {
+        if self.lock_timer.is_none() {
+            return;
+        }
+        if rotation && self.current_piece.y <= self.lowest_y {
+            self.spins_in_place += 1;
+        }
+        if self.spins_in_place > MAX_SPINS_IN_PLACE || self.lock_resets >= MAX_LOCK_RESETS {
+            return;
+        }
+        self.lock_resets += 1;
+        self.lock_timer = Some(Instant::now());
+    }
+
+    fn piece_collides(&self) -> bool {
+        let piece = &self.current_piece;
+        self.shape_collides(&piece.shape, piece.x, piece.y)
+    }
+
+    // The rows above the board are open, as far as the walls go up
+    pub(crate) fn shape_collides(&self, shape: &[Vec<bool>], x: usize, y: isize) -> bool {
+        for (dy, row) in shape.iter().enumerate() {
+            for (dx, &cell) in row.iter().enumerate() {
+                if cell {
+                    let board_x = x + dx;
+                    let board_y = y + dy as isize;
+                    if board_x >= BOARD_WIDTH || board_y >= BOARD_HEIGHT as isize {
+                        return true;
+                    }
+                    if board_y >= 0 && self.board[board_y as usize][board_x].is_filled() {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // The row the current piece would land on if dropped straight down
+    pub(crate) fn ghost_y(&self) -> isize {
+        let piece = &self.current_piece;
+        let mut y = piece.y;
+        while !self.shape_collides(&piece.shape, piece.x, y + 1) {
+            y += 1;
+        }
+        y
+    }
+
+    pub(crate) fn hard_drop(&mut self) {
+        if self.game_over {
+            return;
+        }
+        let ghost_y = self.ghost_y();
+        self.stats
+            .record_moves((ghost_y - self.current_piece.y + 1) as u32);
+        if (ghost_y - self.current_piece.y) as usize >= TRAIL_MIN_ROWS {
+            self.leave_trail(self.current_piece.y..ghost_y);
+        }
+        if ghost_y != self.current_piece.y {
+            self.current_piece.spun = false;
+        }
+        self.current_piece.y = ghost_y;
+        self.current_piece.visual_x = self.current_piece.x as f32;
+        self.current_piece.visual_y = self.current_piece.y as f32;
+        self.lock_piece();
+        self.displayed_score = self.score as f32;
+    }
+
+    // Marks the cells the current piece covers at each of `rows`
+    fn leave_trail(&mut self, rows: std::ops::Range<isize>) {
+        if self.config.reduced_motion {
+            return;
+        }
+        let now = Instant::now();
+        let piece = &self.current_piece;
+        for y in rows {
+            for (dy, row) in piece.shape.iter().enumerate() {
+                for (dx, &cell) in row.iter().enumerate() {
+                    if let (true, Ok(y)) = (cell, usize::try_from(y + dy as isize)) {
+                        self.trail.push((piece.x + dx, y, now));
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether the piece actually moved
+    pub(crate) fn move_piece(&mut self, dx: i32, dy: i32) -> bool {
+        let from = (self.current_piece.x, self.current_piece.y);
+        self.current_piece.x = (self.current_piece.x as i32 + dx).max(0) as usize;
+        self.current_piece.y += dy as isize;
+        let collides = self.piece_collides();
+        if collides {
+            self.current_piece.x = (self.current_piece.x as i32 - dx).max(0) as usize;
+            self.current_piece.y -= dy as isize;
+        } else if dy != 0 {
+            // Sliding into a new row would draw the piece in cells it never visited
+            self.current_piece.visual_x = self.current_piece.x as f32;
+        }
+        self.assert_board_valid();
+        let moved = !collides && from != (self.current_piece.x, self.current_piece.y);
+        if moved {
+            self.current_piece.spun = false;
+        }
+        moved
+    }
+
+    fn can_move(&self, dx: i32, dy: i32) -> bool {
+        let piece = &self.current_piece;
+        match piece.x.checked_add_signed(dx as isize) {
+            Some(x) => !self.shape_collides(&piece.shape, x, piece.y + dy as isize),
+            None => false,
+        }
+    }
+
+    // Makes the press's move, then whatever repeats have come due while the key
+    // stays down. Repeats into a wall are dropped rather than saved up, except
+    // with no repeat delay, which keeps pushing against it
+    pub(crate) fn repeat_held(
+        &mut self,
+        event: InputEvent,
+        (dx, dy): (i32, i32),
+        held: &mut HeldKey,
+        (delay, interval): (Duration, Duration),
+    ) {
+        if held.moves == 0 {
+            self.apply_input(event);
+            held.moves = 1;
+        }
+        if self.mode == GameMode::TrainingWheels {
+            return;
+        }
+        let due = handling::moves_due(held.since.elapsed(), delay, interval);
+        while held.moves < due {
+            if !self.can_move(dx, dy) {
+                if !interval.is_zero() {
+                    held.moves = due;
+                }
+                break;
+            }
+            self.apply_input(event);
+            held.moves += 1;
+        }
+    }
+
+    // A move the player made, which counts toward the session's moves
+    fn player_move(&mut self, dx: i32, dy: i32) {
+        if self.move_piece(dx, dy) {
+            self.stats.record_moves(1);
+            if dx != 0 {
+                self.extend_lock_delay(false);
+            }
+        }
+    }
+
+    pub(crate) fn rotate_piece(&mut self, dir: RotationDir) {
+        let system = self.config.rotation_system;
+        let kind = self.current_piece.kind;
+        let old_shape = self.current_piece.shape.clone();
+        let old_x = self.current_piece.x;
+        let old_rotation = self.current_piece.rotation;
+        if system.two_state(kind) && old_shape != kind.shape() {
+            self.current_piece.shape = kind.shape();
+            self.current_piece.rotation = 0;
+        } else if dir == RotationDir::Clockwise {
+            self.current_piece.shape = rotated(&old_shape);
+            self.current_piece.rotation = (old_rotation + 1) % 4;
+        } else {
+            self.current_piece.shape = rotated_counterclockwise(&old_shape);
+            self.current_piece.rotation = (old_rotation + 3) % 4;
+        }
+        for &kick in system.kicks() {
+            let Some(x) = old_x.checked_add_signed(kick as isize) else {
+                continue;
+            };
+            self.current_piece.x = x;
+            if !self.piece_collides() {
+                self.current_piece.visual_x = x as f32;
+                // The new shape starts drawn a quarter turn back and sweeps into place
+                let start = match dir {
+                    RotationDir::Clockwise => -std::f32::consts::FRAC_PI_2,
+                    RotationDir::Counterclockwise => std::f32::consts::FRAC_PI_2,
+                };
+                if !self.config.reduced_motion {
+                    self.current_piece.rotation_anim = Some((start, 0.0, Instant::now()));
+                }
+                self.current_piece.spun = true;
+                self.stats.record_moves(1);
+                self.extend_lock_delay(true);
+                self.assert_board_valid();
+                return;
+            }
+        }
+        // Back to how it was called, which may be a spawn that is about to top out
+        self.current_piece.shape = old_shape;
+        self.current_piece.x = old_x;
+        self.current_piece.rotation = old_rotation;
+    }
+
+    pub(crate) fn lock_piece(&mut self) {
+        // Only pieces the player pressed keys for are judged, which leaves out
+        // the bot and its lookahead clones
+        let presses = std::mem::take(&mut self.finesse_presses);
+        if presses > 0 {
+            let piece = &self.current_piece;
+            let optimal = self
+                .finesse
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| FinesseAnalyzer::new(&self.config))
+                .optimal_presses(piece.kind, piece.x, &piece.shape)
+                .unwrap_or(presses);
+            self.stats.finesse_errors += presses.saturating_sub(optimal);
+        }
+        let holes_before = self.board_holes();
+        self.t_spin = self.is_t_spin();
+        if self.t_spin {
+            self.stats.t_spins += 1;
+        }
+        let cells = self.lock_cells();
+        let full_rows = self.detect_clears();
+        self.apply_clears(full_rows.clone());
+        let holes_after = self.board_holes();
+        self.stats.total_cells_placed += cells.len() as u32;
+        self.stats.pieces += 1;
+        self.stats.piece_counts[self.current_piece.kind as usize] += 1;
+        self.stats.total_cells_wasted += holes_after.saturating_sub(holes_before);
+
+        // Follow the surviving cells down past the rows that were cleared below them
+        let cells: Vec<_> = cells
+            .into_iter()
+            .filter(|(_, y)| !full_rows.contains(y))
+            .map(|(x, y)| (x, y + full_rows.iter().filter(|&&row| row > y).count()))
+            .collect();
+        if !self.config.reduced_motion {
+            let born = Instant::now();
+            self.locked_cells_flash = Some((cells.clone(), born));
+            self.glow_cells
+                .extend(cells.iter().map(|&(x, y)| GlowCell { x, y, born }));
+            let excess = self.glow_cells.len().saturating_sub(MAX_GLOW_CELLS);
+            self.glow_cells.drain(..excess);
+        }
+        #[cfg(feature = "sound")]
+        if self.config.sound_effects {
+            self.lock_sound = Some(self.current_piece.kind);
+        }
+        if self.config.placement_feedback {
+            self.push_placement_feedback(cells, !full_rows.is_empty(), holes_before, holes_after);
+        }
+        // Once a line is cleared the stack no longer lines up with any opening
+        if self.config.detect_openers
+            && self.opener.is_none()
+            && self.lines_cleared == 0
+            && OPENER_PIECES.contains(&self.stats.pieces)
+        {
+            if let Some(name) = OpenerDetector::builtin().detect(&self.board) {
+                self.opener = Some((name, Instant::now()));
+            }
+        }
+        if self.config.power_ups {
+            let event = self.events.on_piece_locked();
+            if let (Some(event), false) = (event, self.config.reduced_motion) {
+                self.score_popups.push(ScorePopup {
+                    text: locale(self.config.language).fill("power_up", &[&event.multiplier]),
+                    value: 10,
+                    offset: -40.0,
+                    rise_speed: 20.0,
+                    born: Instant::now(),
+                });
+            }
+        }
+        if let Some(challenge) = &self.challenge {
+            self.pieces_used += 1;
+            if self.lines_cleared >= challenge.target_lines
+                || self.pieces_used >= challenge.max_pieces
+            {
+                self.game_over = true;
+            }
+        }
+        self.spawn_piece();
+        // A lock that clears nothing lets whatever garbage is left rise
+        if full_rows.is_empty() && self.pending_garbage > 0 {
+            let lines = std::mem::take(&mut self.pending_garbage);
+            self.inject_garbage(lines);
+        }
+        self.assert_board_valid();
+    }
+
+    // Debug builds check the board and the piece after every change, so a logic
+    // bug fails where it happens instead of corrupting the game later on
+    #[cfg(debug_assertions)]
+    fn assert_board_valid(&self) {
+        // coverage: ignore
+        assert_eq!(self.board.len(), BOARD_HEIGHT, "board has the wrong height");
+        for (y, row) in self.board.iter().enumerate() {
+            // coverage: ignore
+            assert_eq!(row.len(), BOARD_WIDTH, "row {y} has the wrong width");
+            // coverage: ignore
+            assert!(row.iter().filter(|cell| cell.is_filled()).count() <= BOARD_WIDTH);
+        }
+        // The piece that topped out is left overlapping the stack
+        if self.game_over {
+            return;
+        }
+        let piece = &self.current_piece;
+        for (dy, row) in piece.shape.iter().enumerate() {
+            for (dx, &cell) in row.iter().enumerate() {
+                if cell {
+                    let (x, y) = (piece.x + dx, piece.y + dy as isize);
+                    // coverage: ignore
+                    assert!(
+                        x < BOARD_WIDTH && y < BOARD_HEIGHT as isize,
+                        "{:?} sticks out of the board at ({x},{y})",
+                        piece.kind
+                    );
+                    // coverage: ignore
+                    assert!(
+                        y < 0 || !self.board[y as usize][x].is_filled(),
+                        "{:?} overlaps the stack at ({x},{y})",
+                        piece.kind
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn assert_board_valid(&self) {}
+
+    fn push_placement_feedback(
+        &mut self,
+        cells: Vec<(usize, usize)>,
+        cleared_lines: bool,
+        holes_before: u32,
+        holes_after: u32,
+    ) {
+        let color = if holes_after > holes_before {
+            Color32::from_rgba_unmultiplied(255, 0, 0, 160)
+        } else if holes_after < holes_before || cleared_lines {
+            Color32::from_rgba_unmultiplied(0, 255, 0, 160)
+        } else {
+            return;
+        };
+        self.placement_feedbacks.push(PlacementFeedback {
+            cells,
+            color,
+            born: Instant::now(),
+        });
+    }
+
+    // Empty cells with a filled cell somewhere above them in the same column
+    pub(crate) fn board_holes(&self) -> u32 {
+        count_holes(self.board.iter().map(|row| row_mask(row)))
+    }
+
+    // What board_holes would be after locking `shape` at (x, y) and clearing
+    // any rows it completes, without touching the board
+    pub(crate) fn holes_after_drop(&self, shape: &[Vec<bool>], x: usize, y: isize) -> u32 {
+        let mut rows: Vec<u16> = self.board.iter().map(|row| row_mask(row)).collect();
+        for (dy, row) in shape.iter().enumerate() {
+            for (dx, &cell) in row.iter().enumerate() {
+                if let (true, Ok(y)) = (cell, usize::try_from(y + dy as isize)) {
+                    rows[y] |= 1 << (x + dx);
+                }
+            }
+        }
+        let full = (1 << BOARD_WIDTH) - 1;
+        count_holes(rows.into_iter().filter(|&mask| mask != full))
+    }
+
+    // Height of each column, measured from the floor to its highest filled cell
+    fn column_heights(&self) -> [usize; BOARD_WIDTH] {
+        let mut heights = [0; BOARD_WIDTH];
+        for (x, height) in heights.iter_mut().enumerate() {
+            if let Some(top) = self.board.iter().position(|row| row[x].is_filled()) {
+                *height = BOARD_HEIGHT - top;
+            }
+        }
+        heights
+    }
+
+    pub(crate) fn aggregate_height(&self) -> u32 {
+        self.column_heights().iter().sum::<usize>() as u32
+    }
+
+    // Sum of the height differences between neighbouring columns
+    pub(crate) fn bumpiness(&self) -> u32 {
+        self.column_heights()
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]) as u32)
+            .sum()
+    }
+
+    pub(crate) fn max_height(&self) -> usize {
+        self.column_heights().into_iter().max().unwrap_or(0)
+    }
+
+    // In king of the hill, whoever has the lower stack holds the crown; a tie
+    // leaves it with nobody
+    pub(crate) fn crown(&self) -> Option<Crown> {
+        if self.mode != GameMode::KingOfTheHill {
+            return None;
+        }
+        let theirs = self.opponent.as_ref()?.stack_height;
+        match self.max_height().cmp(&theirs) {
+            std::cmp::Ordering::Less => Some(Crown::Ours),
+            std::cmp::Ordering::Greater => Some(Crown::Theirs),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    // Garbage sent while holding the crown is multiplied by this
+    fn attack_power(&self) -> f32 {
+        if self.crown() == Some(Crown::Ours) {
+            KING_OF_THE_HILL_POWER
+        } else {
+            1.0
+        }
+    }
+
+    // Gravity ticks to each metronome beat, more than one once they come too fast
+    // to blink on every one
+    pub(crate) fn metronome_ticks_per_beat(&self) -> u32 {
+        let ticks = METRONOME_MIN_BEAT.as_secs_f32() / self.update_interval.as_secs_f32();
+        (ticks.ceil() as u32).max(1)
+    }
+
+    // Full on each beat, fading to nothing by the next
+    pub(crate) fn metronome_alpha(&self, now: Instant) -> f32 {
+        let ticks_per_beat = self.metronome_ticks_per_beat();
+        let into_tick =
+            ((now - self.last_update).as_secs_f32() / self.update_interval.as_secs_f32()).min(1.0);
+        let beat =
+            ((self.gravity_ticks % ticks_per_beat) as f32 + into_tick) / ticks_per_beat as f32;
+        1.0 - beat.min(1.0)
+    }
+
+    // Nothing below SHAKE_FROM of the board filled, then up to SHAKE_MAX_AMPLITUDE at SHAKE_FULL
+    pub(crate) fn shake_amplitude(&self) -> f32 {
+        if self.config.reduced_motion || self.game_over || self.paused {
+            return 0.0;
+        }
+        let filled = self.max_height() as f32 / BOARD_HEIGHT as f32;
+        let t = ((filled - SHAKE_FROM) / (SHAKE_FULL - SHAKE_FROM)).clamp(0.0, 1.0);
+        t * SHAKE_MAX_AMPLITUDE
+    }
+
+    // Locked cells, garbage included
+    pub(crate) fn pieces_on_board(&self) -> usize {
+        self.board
+            .iter()
+            .flatten()
+            .filter(|cell| cell.is_filled())
+            .count()
+    }
+
+    pub(crate) fn board_filled_fraction(&self) -> f32 {
+        self.pieces_on_board() as f32 / (BOARD_WIDTH * BOARD_HEIGHT) as f32
+    }
+
+    // A T that turned into where it is with three of the four cells diagonal to
+    // its middle taken. The walls and the floor count as taken, and the rows
+    // above the board as open
+    fn is_t_spin(&self) -> bool {
+        let piece = &self.current_piece;
+        if piece.kind != TetrominoType::T || !piece.spun {
+            return false;
+        }
+        let filled = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && piece
+                    .shape
+                    .get(y as usize)
+                    .and_then(|row| row.get(x as usize))
+                    == Some(&true)
+        };
+        // The middle is the one cell that touches the other three
+        let Some((cx, cy)) = (0..piece.shape.len() as isize)
+            .flat_map(|y| (0..piece.shape[0].len() as isize).map(move |x| (x, y)))
+            .find(|&(x, y)| {
+                filled(x, y)
+                    && [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                        .iter()
+                        .filter(|&&(dx, dy)| filled(x + dx, y + dy))
+                        .count()
+                        == 3
+            })
+        else {
+            return false;
+        };
+        let (cx, cy) = (piece.x as isize + cx, piece.y + cy);
+        let taken = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+            .iter()
+            .filter(|&&(dx, dy)| {
+                let (x, y) = (cx + dx, cy + dy);
+                if y < 0 {
+                    return false;
+                }
+                x < 0
+                    || x >= BOARD_WIDTH as isize
+                    || y >= BOARD_HEIGHT as isize
+                    || self.board[y as usize][x as usize].is_filled()
+            })
+            .count();
+        taken >= 3
+    }
+
+    // Writes the current piece into the board where it is, returning the cells it
+    // took. A piece that locks partly above the board tops the game out
+    fn lock_cells(&mut self) -> Vec<(usize, usize)> {
+        let piece = &self.current_piece;
+        let mut cells = Vec::new();
+        for (dy, row) in piece.shape.iter().enumerate() {
+            for (dx, &cell) in row.iter().enumerate() {
+                if !cell {
+                    continue;
+                }
+                match usize::try_from(piece.y + dy as isize) {
+                    Ok(y) => cells.push((piece.x + dx, y)),
+                    Err(_) => self.game_over = true,
+                }
+            }
+        }
+        for &(x, y) in &cells {
+            self.board[y][x] = CellState::Filled(piece.kind);
+        }
+        cells
+    }
+
+    // The full rows, top to bottom
+    pub(crate) fn detect_clears(&self) -> Vec<usize> {
+        (0..BOARD_HEIGHT)
+            .filter(|&y| row_is_full(&self.board[y]))
+            .collect()
+    }
+
+    // Removes the rows and scores them as one clear. A lock clears once, but a
+    // mechanic that clears again afterwards can call this for each further
+    // clear, and each is scored on its own and counts toward the combo. An empty
+    // list is a lock that cleared nothing, so it breaks the combo
+    pub(crate) fn apply_clears(&mut self, mut rows: Vec<usize>) {
+        rows.sort_unstable();
+        rows.dedup();
+        for &y in rows.iter().rev() {
+            self.board.remove(y);
+        }
+        let lines_cleared = rows.len() as u32;
+        for _ in 0..lines_cleared {
+            self.board.insert(0, vec![CellState::Empty; BOARD_WIDTH]);
+        }
+        let t_spin = std::mem::take(&mut self.t_spin);
+        let mut points = self
+            .config
+            .scoring
+            .compute(lines_cleared, t_spin, self.level);
+        if ClearType::from_lines(lines_cleared, t_spin, false).is_difficult() && self.back_to_back {
+            points += self.config.scoring.back_to_back_bonus(points);
+        }
+        self.score += (points as f32 * self.events.multiplier()) as u32;
+        if lines_cleared > 0 {
+            tracing::info!("cleared {} lines, score now {}", lines_cleared, self.score);
+            let t = locale(self.config.language);
+            self.announce(if lines_cleared == 1 {
+                t.get("announce_cleared_line").to_string()
+            } else {
+                t.fill("announce_cleared_lines", &[&lines_cleared])
+            });
+            self.announce(t.fill("announce_score", &[&self.score]));
+        }
+
+        self.lines_cleared += lines_cleared;
+        self.stats.lines += lines_cleared;
+        let perfect_clear =
+            lines_cleared > 0 && self.board.iter().flatten().all(|cell| !cell.is_filled());
+        if perfect_clear {
+            self.stats.perfect_clears += 1;
+        }
+        // The combo and back-to-back still describe the clears before this one
+        let attack = garbage::compute_garbage(
+            ClearType::from_lines(lines_cleared, t_spin, perfect_clear),
+            self.back_to_back,
+            self.combo,
+        );
+        let cancelled = u32::from(attack).min(self.pending_garbage);
+        self.pending_garbage -= cancelled;
+        // Nothing sends it on offline, so a long game would otherwise overflow
+        self.outgoing_garbage = self
+            .outgoing_garbage
+            .saturating_add(attack - cancelled as u8);
+        let level = 1 + self.lines_cleared / LINES_PER_LEVEL;
+        if level > self.level && self.mode != GameMode::Manual {
+            self.level = level;
+            self.level_up_at = Some(Instant::now());
+            self.announce(locale(self.config.language).fill("announce_level", &[&level]));
+        }
+        self.refresh_speed();
+
+        self.push_score_popups(lines_cleared, t_spin);
+    }
+
+    pub(crate) fn refresh_speed(&mut self) {
+        let curve = self.config.gravity_curve;
+        self.update_interval = match self.config.speed_mode {
+            SpeedMode::Level => gravity_interval(self.level, curve),
+            SpeedMode::Score => gravity::score_interval(self.score, curve),
+        };
+    }
+
+    fn push_score_popups(&mut self, lines_cleared: u32, t_spin: bool) {
+        let t = locale(self.config.language);
+        let mut labels = Vec::new();
+        if lines_cleared == 0 {
+            self.combo = 0;
+            // A T-spin that clears nothing is still worth showing
+            if !t_spin {
+                return;
+            }
+            labels.push((t.get("t_spin").to_string(), 1));
+        } else {
+            self.combo += 1;
+            self.stats.max_combo = self.stats.max_combo.max(self.combo - 1);
+
+            let (clear, value) = match lines_cleared {
+                1 => (t.get("single"), 1),
+                2 => (t.get("double"), 2),
+                3 => (t.get("triple"), 3),
+                _ => (t.get("tetris"), 4),
+            };
+            labels.push(if t_spin {
+                (t.fill("t_spin_clear", &[&clear]), value + 1)
+            } else {
+                (clear.to_string(), value)
+            });
+            let difficult = ClearType::from_lines(lines_cleared, t_spin, false).is_difficult();
+            if difficult && self.back_to_back {
+                labels.push((t.get("back_to_back").to_string(), 5));
+            }
+            self.back_to_back = difficult;
+            if self.combo > 1 {
+                labels.push((t.fill("combo", &[&(self.combo - 1)]), 3 + self.combo));
+            }
+        }
+        if self.config.reduced_motion {
+            return;
+        }
+
+        let born = Instant::now();
+        for (i, (text, value)) in labels.into_iter().enumerate() {
+            self.score_popups.push(ScorePopup {
+                text,
+                value,
+                offset: i as f32 * 22.0,
+                rise_speed: 40.0 - i as f32 * 8.0,
+                born,
+            });
+        }
+        self.score_popups.sort_by_key(|popup| popup.value);
+    }
+
+    pub(crate) fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+        let now = Instant::now();
+        tracing::trace!(
+            "tick: piece at ({}, {}), score {}",
+            self.current_piece.x,
+            self.current_piece.y,
+            self.score
+        );
+        self.events.expire();
+        if self.has_gravity() && now - self.last_update >= self.update_interval && !self.game_over {
+            self.apply_input(InputEvent::Gravity);
+            self.last_update = now;
+            self.gravity_ticks = self.gravity_ticks.wrapping_add(1);
+        }
+        // However the piece came to rest, it gets the full lock delay from then
+        // on. Lifting off the stack doesn't stop the clock unless the piece then
+        // lands lower than before, so climbing up can't restart it either.
+        // Training wheels waits for the player instead
+        if !self.game_over && self.mode != GameMode::TrainingWheels {
+            let piece = &self.current_piece;
+            if self.shape_collides(&piece.shape, piece.x, piece.y + 1) {
+                if piece.y > self.lowest_y {
+                    self.lowest_y = piece.y;
+                    self.lock_resets = 0;
+                    self.spins_in_place = 0;
+                    self.lock_timer = Some(now);
+                }
+                let resting_since = *self.lock_timer.get_or_insert(now);
+                if now - resting_since >= LOCK_DELAY {
+                    self.apply_input(InputEvent::Lock);
+                }
+            }
+        }
+        if let Some(network) = self.network.clone() {
+            for packet in network.poll() {
+                match packet {
+                    Packet::Game(message) if !self.game_over => {
+                        self.pending_garbage += message.garbage_lines as u32;
+                    }
+                    Packet::Snapshot(snapshot) => self.opponent = Some(snapshot),
+                    _ => {}
+                }
+            }
+            if self.snapshot_due(now) {
+                network.send_packet(Packet::Snapshot(self.snapshot()));
+            }
+        }
+        if !self.game_over {
+            self.sample_lpm(now);
+        }
+        if self.ai_mode && !self.game_over && now - self.last_bot_move >= BOT_MOVE_INTERVAL {
+            self.play_bot_move();
+            self.last_bot_move = now;
+        }
+    }
+
+    // At the configured rate while the game goes on, then once more straight
+    // away when it ends, and never after that
+    fn snapshot_due(&mut self, now: Instant) -> bool {
+        let rate = self.config.snapshot_rate.max(1);
+        let due = now - self.last_snapshot >= Duration::from_secs(1) / rate;
+        if self.final_snapshot_sent || !(due || self.game_over) {
+            return false;
+        }
+        self.last_snapshot = now;
+        self.final_snapshot_sent = self.game_over;
+        true
+    }
+
+    pub(crate) fn snapshot(&self) -> BoardSnapshot {
+        let mut board = self.board.clone();
+        let piece = &self.current_piece;
+        if !self.game_over {
+            for (dy, row) in piece.shape.iter().enumerate() {
+                for (dx, &cell) in row.iter().enumerate() {
+                    if let (true, Ok(y)) = (cell, usize::try_from(piece.y + dy as isize)) {
+                        board[y][piece.x + dx] = CellState::Filled(piece.kind);
+                    }
+                }
+            }
+        }
+        BoardSnapshot {
+            username: self.config.username.clone(),
+            board,
+            score: self.score,
+            level: self.level,
+            lines: self.lines_cleared,
+            game_over: self.game_over,
+            stack_height: self.max_height(),
+            opponent: false,
+        }
+    }
+
+    pub(crate) fn start_benchmark(&mut self) {
+        let run = BenchmarkRun::default();
+        let finished_games = run.finished_games.clone();
+        let result = run.result.clone();
+        let config = self.config.clone();
+        std::thread::spawn(move || {
+            let outcome = headless::benchmark(&config, BENCHMARK_GAMES, &finished_games);
+            *result.lock().unwrap() = Some(outcome);
+        });
+        self.mode = GameMode::Benchmark;
+        self.benchmark = Some(run);
+    }
+
+    // Queued only while the screen reader is on, since nothing else takes them
+    // off the queue
+    fn announce(&mut self, text: String) {
+        if self.config.screen_reader {
+            self.announcements.push(text);
+        }
+    }
+
+    // Once, the first frame after the game ends: records the results, opens the
+    // end screen and starts wiping the board
+    pub(crate) fn finish_game(&mut self) {
+        tracing::warn!("game over at level {}", self.level);
+        let t = locale(self.config.language);
+        self.announce(t.fill("announce_game_over", &[&self.score]));
+        self.config.games_played += 1;
+        if self.counts_for_records() {
+            self.config.high_scores.record(HighScoreEntry {
+                name: self.config.username.clone(),
+                mode: format!("{:?}", self.mode),
+                day: daily::today(),
+                pieces_per_second: self.stats.pieces_per_second(),
+                finesse_errors: self.stats.finesse_errors,
+                score: self.score,
+                level: self.level,
+                lines: self.lines_cleared,
+                efficiency: self.stats.efficiency(),
+                lines_per_minute: self.stats.lines_per_minute(),
+            });
+        }
+        let mut screen = self.end_screen();
+        if self.mode == GameMode::DailyChallenge {
+            screen.score_best = self
+                .config
+                .high_scores
+                .daily_best
+                .record(self.daily_day, self.score);
+        }
+        self.end_screen = Some(screen);
+        let mut current_row = BOARD_HEIGHT as i32 - 1;
+        if self.config.reduced_motion {
+            self.board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+            current_row = -1;
+        }
+        self.game_over_anim = Some(GameOverClearAnim {
+            current_row,
+            last_clear: Instant::now(),
+        });
+    }
+
+    // The results for the end screen, setting new records unless the game was
+    // a challenge, a walkthrough or played by the bot
+    fn end_screen(&mut self) -> EndScreen {
+        let stats = &self.stats;
+        let time = format!(
+            "{}:{:02}",
+            stats.play_time.as_secs() / 60,
+            stats.play_time.as_secs() % 60
+        );
+        let results = vec![
+            (
+                "lines",
+                self.lines_cleared.to_string(),
+                Some(self.lines_cleared as f32),
+            ),
+            (
+                "level_reached",
+                self.level.to_string(),
+                Some(self.level as f32),
+            ),
+            (
+                "stats_pieces",
+                stats.pieces.to_string(),
+                Some(stats.pieces as f32),
+            ),
+            (
+                "stats_efficiency",
+                format!("{:.0}%", stats.efficiency() * 100.0),
+                None,
+            ),
+            (
+                "max_combo",
+                stats.max_combo.to_string(),
+                Some(stats.max_combo as f32),
+            ),
+            (
+                "stats_t_spins",
+                stats.t_spins.to_string(),
+                Some(stats.t_spins as f32),
+            ),
+            (
+                "pieces_per_second",
+                format!("{:.2}", stats.pieces_per_second()),
+                Some(stats.pieces_per_second()),
+            ),
+            (
+                "lines_per_minute",
+                format!("{:.1}", stats.lines_per_minute()),
+                Some(stats.lines_per_minute()),
+            ),
+            (
+                "actions_per_minute",
+                format!("{:.0}", stats.actions_per_minute()),
+                Some(stats.actions_per_minute()),
+            ),
+            (
+                "perfect_clear",
+                stats.perfect_clears.to_string(),
+                Some(stats.perfect_clears as f32),
+            ),
+            ("stats_time", time, None),
+        ];
+        let counts = self.counts_for_records();
+        EndScreen::new(
+            self.score,
+            results,
+            counts.then_some(&mut self.config.personal_bests),
+        )
+    }
+
+    // Personal bests and high scores only come from the player's own ordinary games
+    fn counts_for_records(&self) -> bool {
+        self.challenge.is_none()
+            && !self.ai_mode
+            && matches!(self.mode, GameMode::Marathon | GameMode::Manual)
+    }
+
+    pub(crate) fn record_rewind_point(&mut self) {
+        let due = self
+            .rewind_history
+            .back()
+            .map_or(Duration::ZERO, |point| point.play_time + REWIND_INTERVAL);
+        if self.stats.play_time < due {
+            return;
+        }
+        if self.rewind_history.len() == REWIND_POINTS {
+            self.rewind_history.pop_front();
+        }
+        self.rewind_history.push_back(RewindPoint {
+            play_time: self.stats.play_time,
+            board: self.board.clone(),
+            current_piece: self.current_piece.clone(),
+            held_piece: self.held_piece,
+            can_hold: self.can_hold,
+            bag: self.bag.clone(),
+            score: self.score,
+            level: self.level,
+            lines_cleared: self.lines_cleared,
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+            update_interval: self.update_interval,
+            pieces_used: self.pieces_used,
+            replay_events: self.replay.lock().unwrap().events.len(),
+        });
+    }
+
+    // Not online, where the opponent has already seen what happened, nor in the
+    // daily challenge, where everyone's score is compared on the same pieces
+    pub(crate) fn rewind_allowed(&self) -> bool {
+        self.network.is_none() && self.mode != GameMode::DailyChallenge
+    }
+
+    // Spends a token to go back to the oldest point kept, unless the game is
+    // over or doesn't allow it
+    pub(crate) fn rewind(&mut self) {
+        if self.rewind_tokens == 0 || self.game_over || !self.rewind_allowed() {
+            return;
+        }
+        let Some(point) = self.rewind_history.pop_front() else {
+            return;
+        };
+        self.rewind_tokens -= 1;
+        self.rewind_history.clear();
+        self.board = point.board;
+        self.current_piece = point.current_piece;
+        self.held_piece = point.held_piece;
+        self.can_hold = point.can_hold;
+        self.bag = point.bag;
+        self.score = point.score;
+        self.level = point.level;
+        self.lines_cleared = point.lines_cleared;
+        self.combo = point.combo;
+        self.back_to_back = point.back_to_back;
+        self.update_interval = point.update_interval;
+        self.pieces_used = point.pieces_used;
+        self.replay
+            .lock()
+            .unwrap()
+            .events
+            .truncate(point.replay_events);
+        self.reset_lock_delay();
+        self.hard_drop_pending = None;
+        self.trail.clear();
+        self.locked_cells_flash = None;
+        self.last_update = Instant::now();
+    }
+
+    // Starts the session counters over without touching the game itself
+    pub(crate) fn reset_stats(&mut self) {
+        self.stats = GameStats::default();
+        self.lpm_history.clear();
+    }
+
+    // Written over the last saved replay each time
+    pub(crate) fn save_replay(&self) -> anyhow::Result<std::path::PathBuf> {
+        let mut replay = self.replay.lock().unwrap().clone();
+        replay.score = self.score;
+        replay.lines = self.lines_cleared;
+        let path = config::data_dir().join("replay.json");
+        replay.save(&path)?;
+        Ok(path)
+    }
+
+    pub(crate) fn export_stats(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(config::data_dir())
+            .context("failed to create the data directory")?;
+        let summary = self.stats.summary(locale(self.config.language));
+        std::fs::write(config::data_dir().join("stats.txt"), summary)
+            .context("failed to write stats.txt")
+    }
+
+    pub(crate) fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        // Don't count the paused time toward the next gravity tick or lock
+        self.last_update = Instant::now();
+        self.lock_timer = None;
+    }
+
+    pub(crate) fn play_bot_move(&mut self) {
+        let Some(placement) = self.config.bot.choose(self) else {
+            return;
+        };
+        for _ in 0..placement.rotation {
+            self.apply_input(InputEvent::Rotate);
+        }
+        let dx = placement.x as i32 - self.current_piece.x as i32;
+        let step = if dx < 0 {
+            InputEvent::MoveLeft
+        } else {
+            InputEvent::MoveRight
+        };
+        for _ in 0..dx.abs() {
+            self.apply_input(step);
+        }
+        self.apply_input(InputEvent::HardDrop);
+    }
+
+    // Only real inputs go through here, never the bot's simulated drops, so
+    // they are the ones the opponent hears about
+    pub(crate) fn apply_input(&mut self, event: InputEvent) {
+        if self.paused {
+            return;
+        }
+        if self.config.strict_mode {
+            if let Err(violation) = strict::validate_action(self, event) {
+                tracing::warn!("refused {:?}: {}", event, violation);
+                return;
+            }
+        }
+        self.replay.lock().unwrap().events.push(ReplayEvent {
+            at_ms: self.started_at.elapsed().as_millis() as u64,
+            event,
+            queued_rotation: self.queued_rotation,
+            queued_hold: self.queued_hold,
+        });
+        let lines_before = self.lines_cleared;
+        // A hard drop spawns the next piece, so note where this one lands first
+        let landed = (event == InputEvent::HardDrop).then(|| {
+            let piece = &self.current_piece;
+            (piece.x, self.ghost_y(), piece.rotation)
+        });
+        match event {
+            InputEvent::MoveLeft => self.player_move(-1, 0),
+            InputEvent::MoveRight => self.player_move(1, 0),
+            InputEvent::SoftDrop => self.player_move(0, 1),
+            InputEvent::Gravity => {
+                let from = self.current_piece.y;
+                if self.move_piece(0, 1) && self.update_interval <= TRAIL_GRAVITY_INTERVAL {
+                    self.leave_trail(from..from + 1);
+                }
+            }
+            InputEvent::Rotate => self.rotate_piece(self.config.rotation_system.primary()),
+            InputEvent::RotateReverse => {
+                self.rotate_piece(self.config.rotation_system.primary().reversed())
+            }
+            InputEvent::Hold => {
+                self.hold_piece();
+                if self.piece_collides() {
+                    self.game_over = true;
+                }
+            }
+            InputEvent::HardDrop => self.hard_drop(),
+            InputEvent::Lock => self.lock_piece(),
+        }
+        if let Some(network) = &self.network {
+            let garbage = std::mem::take(&mut self.outgoing_garbage);
+            network.send(event, (garbage as f32 * self.attack_power()).round() as u8);
+        }
+        if let Some(step) = self.tutorial_step {
+            if !self.ai_mode && step.done_by(event, self.lines_cleared - lines_before) {
+                self.advance_tutorial(step);
+            }
+        }
+        self.log_move(event, landed);
+    }
+
+    fn advance_tutorial(&mut self, step: TutorialStep) {
+        self.tutorial_step = step.next();
+        if self.tutorial_step.is_some() {
+            return;
+        }
+        self.score += tutorial::TUTORIAL_BONUS;
+        self.config.tutorial_completed = true;
+        let text =
+            locale(self.config.language).fill("tutorial_complete", &[&tutorial::TUTORIAL_BONUS]);
+        self.announce(text.clone());
+        self.score_popups.push(ScorePopup {
+            text,
+            value: tutorial::TUTORIAL_BONUS,
+            offset: 0.0,
+            rise_speed: 30.0,
+            born: Instant::now(),
+        });
+    }
+
+    fn log_move(&mut self, event: InputEvent, landed: Option<(usize, isize, u8)>) {
+        let piece = &self.current_piece;
+        let (x, y, rotation) = landed.unwrap_or((piece.x, piece.y, piece.rotation));
+        let entry = format!(
+            "T+{}: {:?} -> ({},{}) rot={}",
+            self.started_at.elapsed().as_millis(),
+            event,
+            x,
+            y,
+            rotation
+        );
+        self.move_log.push_back((event, entry));
+        if self.move_log.len() > MOVE_LOG_CAPACITY {
+            self.move_log.pop_front();
+        }
+    }
+
+    // Pushes the stack up by `lines` rows, each with a single random gap that
+    // never lines up with the one in the row below it
+    fn inject_garbage(&mut self, lines: u32) {
+        self.inject_garbage_from(lines, &mut rand::thread_rng());
+    }
+
+    fn inject_garbage_from(&mut self, lines: u32, rng: &mut impl Rng) {
+        let mut last_gap = None;
+        for _ in 0..lines {
+            if self.board[0].iter().any(|cell| cell.is_filled()) {
+                self.game_over = true;
+            }
+            self.board.remove(0);
+            let mut gap = rng.gen_range(0..BOARD_WIDTH);
+            while Some(gap) == last_gap {
+                gap = rng.gen_range(0..BOARD_WIDTH);
+            }
+            last_gap = Some(gap);
+            let mut row = vec![CellState::Garbage; BOARD_WIDTH];
+            row[gap] = CellState::Empty;
+            self.board.push(row);
+        }
+        while self.piece_collides() && self.current_piece.y > 0 {
+            self.current_piece.y -= 1;
+        }
+        if self.piece_collides() {
+            self.game_over = true;
+        }
+        self.current_piece.visual_y = self.current_piece.y as f32;
+    }
+
+    fn sample_lpm(&mut self, now: Instant) {
+        let due = match self.lpm_history.back() {
+            Some(&(last, _)) => now - last >= Duration::from_secs(1),
+            None => true,
+        };
+        if due {
+            self.lpm_history.push_back((now, self.lines_cleared));
+        }
+        while let Some(&(oldest, _)) = self.lpm_history.front() {
+            if now - oldest <= LPM_WINDOW {
+                break;
+            }
+            self.lpm_history.pop_front();
+        }
+    }
+
+    // (seconds ago, lines per minute) for each sample in the window
+    pub(crate) fn lpm_points(&self) -> Vec<(f32, f32)> {
+        let samples: Vec<_> = self.lpm_history.iter().collect();
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, &&(at, lines))| {
+                let (since, lines_then) = *samples[i.saturating_sub(LPM_SMOOTHING)];
+                let minutes = (at - since).as_secs_f32() / 60.0;
+                let lpm = if minutes > 0.0 {
+                    (lines - lines_then) as f32 / minutes
+                } else {
+                    0.0
+                };
+                (at.elapsed().as_secs_f32(), lpm)
+            })
+            .collect()
+    }
+
+    // Glides the drawn piece toward its logical row, faster when it lags behind
+    pub(crate) fn animate(&mut self, dt: f32) {
+        if self.config.reduced_motion {
+            self.current_piece.visual_x = self.current_piece.x as f32;
+            self.current_piece.visual_y = self.current_piece.y as f32;
+            self.displayed_score = self.score as f32;
+            // Anything started before the setting was switched on just goes away
+            self.current_piece.rotation_anim = None;
+            self.score_popups.clear();
+            self.glow_cells.clear();
+            self.locked_cells_flash = None;
+            self.trail.clear();
+            self.placement_feedbacks
+                .retain(|feedback| feedback.born.elapsed() < PLACEMENT_FEEDBACK_DURATION);
+            return;
+        }
+        let target = self.current_piece.y as f32;
+        let distance = (target - self.current_piece.visual_y).abs().max(1.0);
+        let mut speed = distance / self.update_interval.as_secs_f32();
+        if self.current_piece.entering() {
+            let entry = self.current_piece.shape.len() as f32 / SPAWN_FALL_IN.as_secs_f32();
+            speed = speed.max(entry);
+        }
+        self.current_piece.visual_y = approach(self.current_piece.visual_y, target, speed * dt);
+
+        let target = self.current_piece.x as f32;
+        self.current_piece.visual_x = if self.config.slide_speed > 0.0 {
+            approach(
+                self.current_piece.visual_x,
+                target,
+                self.config.slide_speed * dt,
+            )
+        } else {
+            target
+        };
+
+        if self.current_piece.rotation_angle().is_none() {
+            self.current_piece.rotation_anim = None;
+        }
+
+        let target = self.score as f32;
+        self.displayed_score = if self.game_over {
+            target
+        } else {
+            let gap = (target - self.displayed_score).abs();
+            approach(self.displayed_score, target, (gap * 10.0 * dt).max(1.0))
+        };
+
+        self.score_popups
+            .retain(|popup| popup.born.elapsed() < SCORE_POPUP_DURATION);
+        self.glow_cells
+            .retain(|glow| glow.born.elapsed() < GLOW_DURATION);
+        self.trail
+            .retain(|&(_, _, passed)| passed.elapsed() < TRAIL_DURATION);
+        if matches!(&self.locked_cells_flash, Some((_, locked)) if locked.elapsed() >= LOCK_FLASH_DURATION)
+        {
+            self.locked_cells_flash = None;
+        }
+        self.placement_feedbacks
+            .retain(|feedback| feedback.born.elapsed() < PLACEMENT_FEEDBACK_DURATION);
+    }
+}
+
+// The shape turned a quarter turn clockwise
+pub(crate) fn rotated(shape: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    let rows = shape.len();
+    let cols = shape[0].len();
+    let mut turned = vec![vec![false; rows]; cols];
+    for (y, row) in shape.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            turned[x][rows - 1 - y] = cell;
+        }
+    }
+    turned
+}
+
+fn rotated_counterclockwise(shape: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    let cols = shape[0].len();
+    let mut turned = vec![vec![false; shape.len()]; cols];
+    for (y, row) in shape.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            turned[cols - 1 - x][y] = cell;
+        }
+    }
+    turned
+}
+
+// One bit per column, set where the cell is filled
+fn row_mask(row: &[CellState]) -> u16 {
+    row.iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.is_filled())
+        .fold(0, |mask, (x, _)| mask | 1 << x)
+}
+
+// Row masks from top to bottom in, covered empty cells out
+fn count_holes(rows: impl Iterator<Item = u16>) -> u32 {
+    let mut covered = 0;
+    let mut holes = 0;
+    for filled in rows {
+        holes += (covered & !filled).count_ones();
+        covered |= filled;
+    }
+    holes
+}
+
+fn row_is_full(row: &[CellState]) -> bool {
+    row.iter().all(|cell| cell.is_filled())
+}
+
+fn approach(current: f32, target: f32, max_step: f32) -> f32 {
+    if (target - current).abs() <= max_step {
+        target
+    } else {
+        current + max_step.copysign(target - current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::ScoringSystem;
+
+    #[test]
+    fn board_holes_counts_covered_empty_cells() {
+        let mut game = TetrisGame::new(Config::default());
+        assert_eq!(game.board_holes(), 0);
+
+        // Column 0: covered at row 17, so rows 18 and 19 are holes
+        game.board[17][0] = CellState::Garbage;
+        // Column 3: resting on the floor, nothing buried
+        game.board[19][3] = CellState::Garbage;
+        // Column 5: two overhangs bury rows 16, 17 and 19
+        game.board[15][5] = CellState::Garbage;
+        game.board[18][5] = CellState::Garbage;
+        // Column 9: only the empty cell between two filled ones counts
+        game.board[10][9] = CellState::Garbage;
+        game.board[11][9] = CellState::Garbage;
+        for y in 13..BOARD_HEIGHT {
+            game.board[y][9] = CellState::Garbage;
+        }
+
+        assert_eq!(game.board_holes(), 6);
+    }
+
+    #[test]
+    fn pieces_spawn_centered() {
+        let cases = [
+            (TetrominoType::I, 3),
+            (TetrominoType::J, 3),
+            (TetrominoType::L, 3),
+            (TetrominoType::O, 4),
+            (TetrominoType::S, 3),
+            (TetrominoType::T, 3),
+            (TetrominoType::Z, 3),
+        ];
+        for (kind, x) in cases {
+            let piece = Tetromino::new(kind);
+            assert_eq!(piece.x, x, "{kind:?} spawn column");
+            let center = piece.x as f32 + piece.shape[0].len() as f32 / 2.0;
+            let off_center = (center - BOARD_WIDTH as f32 / 2.0).abs();
+            assert!(off_center <= 1.0, "{kind:?} spawns {off_center} off center");
+        }
+    }
+
+    fn shape(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter()
+            .map(|row| row.chars().map(|c| c == '#').collect())
+            .collect()
+    }
+
+    #[test]
+    fn piece_centers() {
+        let t = Tetromino::new(TetrominoType::T);
+        assert_eq!(t.center_of_mass(), egui::vec2(1.5, 1.25));
+        assert_eq!(t.rotation_center(), egui::vec2(1.5, 1.0));
+        let o = Tetromino::new(TetrominoType::O);
+        assert_eq!(o.center_of_mass(), o.rotation_center());
+        let mut i = Tetromino::new(TetrominoType::I);
+        i.shape = rotated(&i.shape);
+        assert_eq!(i.center_of_mass(), egui::vec2(1.5, 2.0));
+        assert_eq!(i.rotation_center(), egui::vec2(1.0, 2.0));
+    }
+
+    // A piece in open space, clear of the walls and floor
+    fn game_with_piece(kind: TetrominoType) -> TetrisGame {
+        let mut game = TetrisGame::new(Config::default());
+        game.current_piece = Tetromino::new(kind);
+        game.current_piece.x = 4;
+        game.current_piece.y = 8;
+        game
+    }
+
+    #[test]
+    fn rotate_piece_turns_every_piece_clockwise() {
+        let cases = [
+            (
+                TetrominoType::I,
+                [
+                    &["####", "...."][..],
+                    &[".#", ".#", ".#", ".#"],
+                    &["....", "####"],
+                    &["#.", "#.", "#.", "#."],
+                ],
+            ),
+            (
+                TetrominoType::J,
+                [
+                    &["#..", "###"][..],
+                    &["##", "#.", "#."],
+                    &["###", "..#"],
+                    &[".#", ".#", "##"],
+                ],
+            ),
+            (
+                TetrominoType::L,
+                [
+                    &["..#", "###"][..],
+                    &["#.", "#.", "##"],
+                    &["###", "#.."],
+                    &["##", ".#", ".#"],
+                ],
+            ),
+            (
+                TetrominoType::O,
+                [
+                    &["##", "##"][..],
+                    &["##", "##"],
+                    &["##", "##"],
+                    &["##", "##"],
+                ],
+            ),
+            (
+                TetrominoType::S,
+                [
+                    &[".##", "##."][..],
+                    &["#.", "##", ".#"],
+                    &[".##", "##."],
+                    &["#.", "##", ".#"],
+                ],
+            ),
+            (
+                TetrominoType::T,
+                [
+                    &[".#.", "###"][..],
+                    &["#.", "##", "#."],
+                    &["###", ".#."],
+                    &[".#", "##", ".#"],
+                ],
+            ),
+            (
+                TetrominoType::Z,
+                [
+                    &["##.", ".##"][..],
+                    &[".#", "##", "#."],
+                    &["##.", ".##"],
+                    &[".#", "##", "#."],
+                ],
+            ),
+        ];
+        for (kind, states) in cases {
+            let mut game = game_with_piece(kind);
+            for (rotation, expected) in states.iter().enumerate() {
+                assert_eq!(
+                    game.current_piece.shape,
+                    shape(expected),
+                    "{kind:?} state {rotation}"
+                );
+                assert_eq!(game.current_piece.rotation, rotation as u8);
+                game.rotate_piece(RotationDir::Clockwise);
+            }
+            // Four quarter turns come back to the spawn shape
+            assert_eq!(game.current_piece.shape, kind.shape(), "{kind:?}");
+            assert_eq!(game.current_piece.rotation, 0);
+
+            // Counterclockwise walks the same states backwards
+            for expected in states.iter().rev() {
+                game.rotate_piece(RotationDir::Counterclockwise);
+                assert_eq!(game.current_piece.shape, shape(expected), "{kind:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_piece_reverts_when_blocked() {
+        // The T's clockwise state needs (4, 10), which is taken
+        let mut game = game_with_piece(TetrominoType::T);
+        game.board[10][4] = CellState::Garbage;
+        game.rotate_piece(RotationDir::Clockwise);
+        assert_eq!(game.current_piece.shape, TetrominoType::T.shape());
+        assert_eq!(game.current_piece.rotation, 0);
+        assert_eq!(game.current_piece.x, 4);
+
+        // An upright I against the right wall has no room to lie flat
+        let mut game = game_with_piece(TetrominoType::I);
+        game.rotate_piece(RotationDir::Clockwise);
+        game.current_piece.x = BOARD_WIDTH - 2;
+        let upright = game.current_piece.shape.clone();
+        game.rotate_piece(RotationDir::Clockwise);
+        assert_eq!(game.current_piece.shape, upright);
+        assert_eq!(game.current_piece.rotation, 1);
+        assert_eq!(game.current_piece.x, BOARD_WIDTH - 2);
+    }
+
+    #[test]
+    fn moves_count_player_actions_but_not_gravity() {
+        let mut game = game_with_piece(TetrominoType::T);
+        game.apply_input(InputEvent::MoveLeft);
+        game.apply_input(InputEvent::Rotate);
+        game.apply_input(InputEvent::Gravity);
+        assert_eq!(game.stats.moves, 2);
+
+        let drop = (game.ghost_y() - game.current_piece.y + 1) as u32;
+        game.apply_input(InputEvent::HardDrop);
+        assert_eq!(game.stats.moves, 2 + drop);
+
+        // Against the wall, so the move goes nowhere
+        game.current_piece.x = 0;
+        game.apply_input(InputEvent::MoveLeft);
+        assert_eq!(game.stats.moves, 2 + drop);
+        assert_eq!(game.stats.actions_per_minute(), (2 + drop) as f32);
+
+        // A minute of play later they've all aged out of the APM
+        game.stats.play_time += APM_WINDOW + Duration::from_secs(1);
+        assert_eq!(game.stats.actions_per_minute(), 0.0);
+    }
+
+    #[test]
+    fn holes_after_drop_matches_a_real_lock() {
+        let mut game = TetrisGame::new(Config::default());
+        // A ledge over column 8, and a bottom row that an I at x = 0 completes
+        game.board[15][8] = CellState::Garbage;
+        game.board[19][4..].fill(CellState::Garbage);
+        for x in 0..=BOARD_WIDTH - 4 {
+            let mut after = game.clone();
+            after.current_piece = Tetromino::new(TetrominoType::I);
+            after.current_piece.x = x;
+            let y = after.ghost_y();
+            let predicted = after.holes_after_drop(&after.current_piece.shape, x, y);
+            after.hard_drop();
+            assert_eq!(predicted, after.board_holes(), "I dropped at x = {x}");
+        }
+    }
+
+    #[test]
+    fn pieces_on_board_counts_every_locked_cell() {
+        let mut game = TetrisGame::new(Config::default());
+        assert_eq!(game.pieces_on_board(), 0);
+        // Two flat I's per row leave columns 8 and 9 open, so nothing clears
+        for i in 0..10 {
+            game.current_piece = Tetromino::new(TetrominoType::I);
+            game.current_piece.x = if i % 2 == 0 { 0 } else { 4 };
+            game.hard_drop();
+        }
+        assert_eq!(game.lines_cleared, 0);
+        assert_eq!(game.pieces_on_board(), 40);
+        assert_eq!(game.board_filled_fraction(), 0.2);
+    }
+
+    #[test]
+    fn starting_garbage_fills_the_bottom_rows() {
+        let game = TetrisGame::new(Config {
+            starting_garbage: 10,
+            ..Config::default()
+        });
+        let gaps: Vec<usize> = game.board[10..]
+            .iter()
+            .map(|row| {
+                let empty: Vec<usize> = (0..BOARD_WIDTH)
+                    .filter(|&x| row[x] == CellState::Empty)
+                    .collect();
+                assert_eq!(empty.len(), 1, "each garbage row has one gap");
+                empty[0]
+            })
+            .collect();
+        assert!(gaps.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!(game.board[..10]
+            .iter()
+            .flatten()
+            .all(|cell| !cell.is_filled()));
+    }
+
+    #[test]
+    fn mercy_rule_raises_a_blocked_spawn_above_the_board() {
+        for mercy_rule in [false, true] {
+            let mut game = TetrisGame::new(Config {
+                mercy_rule,
+                ..Config::default()
+            });
+            // Block the row of the next piece's lowest cells, which it clears a row higher
+            let (kind, _) = game.bag.preview(1).next().unwrap();
+            let spawn = Tetromino::new(kind);
+            let bottom = spawn.shape.iter().rposition(|row| row.contains(&true));
+            game.board[bottom.unwrap()][1..].fill(CellState::Garbage);
+            game.spawn_piece();
+            assert_eq!(game.game_over, !mercy_rule);
+            if mercy_rule {
+                assert_eq!((game.current_piece.x, game.current_piece.y), (spawn.x, -1));
+                assert!(!game.piece_collides());
+                // It has nowhere to fall, and locking with a row above the board tops out
+                game.hard_drop();
+                assert!(game.game_over);
+            }
+        }
+    }
+
+    #[test]
+    fn held_moves_repeat_after_das() {
+        let mut game = TetrisGame::new(Config::default());
+        let x = game.current_piece.x;
+        let handling = game.config.handling;
+        let timing = (handling.das(), handling.arr());
+        let mut held = HeldKey::new(Instant::now());
+        game.repeat_held(InputEvent::MoveRight, (1, 0), &mut held, timing);
+        game.repeat_held(InputEvent::MoveRight, (1, 0), &mut held, timing);
+        assert_eq!(game.current_piece.x, x + 1);
+
+        // Held past DAS with an ARR of zero, it goes straight to the wall
+        let mut held = HeldKey::new(Instant::now() - handling.das());
+        game.repeat_held(
+            InputEvent::MoveLeft,
+            (-1, 0),
+            &mut held,
+            (handling.das(), Duration::ZERO),
+        );
+        assert_eq!(game.current_piece.x, 0);
+    }
+
+    #[test]
+    fn new_pieces_slide_in_from_above() {
+        let mut game = TetrisGame::new(Config::default());
+        game.spawn_piece();
+        assert!(game.current_piece.entering());
+        game.animate(SPAWN_FALL_IN.as_secs_f32() / 2.0);
+        assert!(game.current_piece.entering());
+        game.animate(SPAWN_FALL_IN.as_secs_f32() / 2.0 + 0.001);
+        assert!(!game.current_piece.entering());
+
+        game.config.reduced_motion = true;
+        game.spawn_piece();
+        assert!(!game.current_piece.entering());
+    }
+
+    #[test]
+    fn training_wheels_waits_for_the_player_to_place() {
+        let mut game = TetrisGame::new(Config::default());
+        game.mode = GameMode::TrainingWheels;
+        game.current_piece.y = game.ghost_y();
+        assert!(game.awaiting_placement());
+        game.last_update = Instant::now() - Duration::from_secs(10);
+        game.lock_timer = Some(Instant::now() - LOCK_DELAY * 2);
+        game.update();
+        assert_eq!(game.stats.pieces, 0);
+        game.apply_input(InputEvent::Lock);
+        assert_eq!(game.stats.pieces, 1);
+        assert!(!game.awaiting_placement());
+    }
+
+    #[test]
+    fn separate_clears_in_one_lock_score_apart() {
+        let mut game = TetrisGame::new(Config::default());
+        game.board[19].fill(CellState::Garbage);
+        game.board[18].fill(CellState::Garbage);
+        let rows = game.detect_clears();
+        assert_eq!(rows, [18, 19]);
+        game.apply_clears(vec![19]);
+        assert_eq!((game.score, game.combo), (100, 1));
+        // What was row 18 has fallen to the bottom, and clearing it is a combo
+        assert_eq!(game.detect_clears(), [19]);
+        game.apply_clears(vec![19]);
+        assert_eq!((game.lines_cleared, game.combo), (2, 2));
+        assert!(game.board.iter().flatten().all(|cell| !cell.is_filled()));
+        game.apply_clears(Vec::new());
+        assert_eq!(game.combo, 0);
+    }
+
+    #[test]
+    fn a_t_turned_into_a_covered_slot_is_a_t_spin() {
+        // Rows 18 and 19 leave a slot for an upside down T, which (3, 17) covers.
+        // (0, 17) keeps either clear from being a perfect clear
+        let lock_in_slot = |covered: bool, moved: bool| {
+            let mut game = game_with_piece(TetrominoType::T);
+            game.config.scoring = ScoringSystem::Guideline;
+            game.board[19].fill(CellState::Garbage);
+            game.board[19][4] = CellState::Empty;
+            game.board[18].fill(CellState::Garbage);
+            game.board[18][3..6].fill(CellState::Empty);
+            game.board[17][0] = CellState::Garbage;
+            if covered {
+                game.board[17][3] = CellState::Garbage;
+            }
+            game.rotate_piece(RotationDir::Clockwise);
+            game.rotate_piece(RotationDir::Clockwise);
+            if moved {
+                game.move_piece(1, 0);
+            }
+            game.current_piece.x = 3;
+            game.current_piece.y = 18;
+            game.lock_piece();
+            (game.stats.t_spins, game.score, game.outgoing_garbage)
+        };
+        assert_eq!(lock_in_slot(true, false), (1, 1200, 4));
+        assert_eq!(lock_in_slot(false, false), (0, 300, 1));
+        assert_eq!(lock_in_slot(true, true), (0, 300, 1));
+    }
+
+    #[test]
+    fn clears_cancel_pending_garbage_before_it_rises() {
+        let mut game = TetrisGame::new(Config::default());
+        game.pending_garbage = 3;
+        // A double attacks with one row, which cancels one of the three. The
+        // cell above keeps it from being a perfect clear
+        game.board[18].fill(CellState::Garbage);
+        game.board[19].fill(CellState::Garbage);
+        game.board[17][0] = CellState::Filled(TetrominoType::I);
+        game.apply_clears(game.detect_clears());
+        assert_eq!((game.pending_garbage, game.outgoing_garbage), (2, 0));
+        assert!(!game.board[..BOARD_HEIGHT - 1]
+            .iter()
+            .flatten()
+            .any(|cell| cell.is_filled()));
+
+        // Nothing cleared this time, so the rest comes up under the stack
+        game.hard_drop();
+        assert_eq!(game.pending_garbage, 0);
+        let garbage_rows = game
+            .board
+            .iter()
+            .filter(|row| row.contains(&CellState::Garbage))
+            .count();
+        assert_eq!(garbage_rows, 2);
+    }
+
+    #[test]
+    fn attacks_beyond_the_pending_garbage_are_sent() {
+        let mut game = TetrisGame::new(Config::default());
+        game.pending_garbage = 3;
+        // A tetris attacks with four rows: three cancel and the last one goes out
+        for y in 16..BOARD_HEIGHT {
+            game.board[y].fill(CellState::Garbage);
+        }
+        game.board[15][0] = CellState::Filled(TetrominoType::I);
+        game.apply_clears(game.detect_clears());
+        assert_eq!((game.pending_garbage, game.outgoing_garbage), (0, 1));
+    }
+
+    #[test]
+    fn board_shakes_harder_as_the_stack_nears_the_top() {
+        let mut game = TetrisGame::new(Config::default());
+        let mut amplitude_at = |height: usize| {
+            for (y, row) in game.board.iter_mut().enumerate() {
+                row[0] = if y >= BOARD_HEIGHT - height {
+                    CellState::Garbage
+                } else {
+                    CellState::Empty
+                };
+            }
+            game.shake_amplitude()
+        };
+        assert_eq!(amplitude_at(15), 0.0);
+        assert!(amplitude_at(17) > 0.0);
+        assert_eq!(amplitude_at(19), SHAKE_MAX_AMPLITUDE);
+        assert_eq!(amplitude_at(20), SHAKE_MAX_AMPLITUDE);
+
+        game.config.reduced_motion = true;
+        assert_eq!(game.shake_amplitude(), 0.0);
+    }
+
+    #[test]
+    fn finesse_is_judged_by_one_analyzer_a_game() {
+        let mut game = TetrisGame::new(Config::default());
+        let bot = game.clone();
+        // Three presses for a piece dropped right where it spawned
+        game.finesse_presses = 3;
+        game.apply_input(InputEvent::HardDrop);
+        assert_eq!(game.stats.finesse_errors, 3);
+        assert!(bot.finesse.lock().unwrap().is_some());
+        game.restart();
+        assert!(game.finesse.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn a_held_rotate_key_turns_one_spawn() {
+        let mut game = TetrisGame::new(Config::default());
+        game.current_piece = Tetromino::new(TetrominoType::T);
+        // Up goes down as the piece is falling, and turns it
+        game.queue_spawn_keys(Some(RotationDir::Clockwise), false);
+        game.rotation_key_spent = true;
+        game.queue_spawn_keys(Some(RotationDir::Clockwise), false);
+        game.spawn_piece();
+        assert_eq!(game.current_piece.rotation, 0);
+
+        // Let go and pressed again before the next spawn, it turns that piece once
+        game.queue_spawn_keys(None, false);
+        game.queue_spawn_keys(Some(RotationDir::Clockwise), false);
+        game.spawn_piece();
+        assert_eq!(game.current_piece.rotation, 1);
+        game.queue_spawn_keys(Some(RotationDir::Clockwise), false);
+        game.spawn_piece();
+        assert_eq!(game.current_piece.rotation, 0);
+    }
+
+    #[test]
+    fn restart_keeps_the_window_and_the_bot() {
+        let mut game = TetrisGame::new(Config::default());
+        game.fullscreen = true;
+        game.windowed_size = Some(egui::vec2(800.0, 600.0));
+        game.ai_mode = true;
+        game.debug_overlay = true;
+        game.score = 100;
+        let export = game.scores_export.clone();
+        game.restart();
+        assert!(game.fullscreen && game.ai_mode && game.debug_overlay);
+        assert!(Arc::ptr_eq(&game.scores_export, &export));
+        assert_eq!(game.windowed_size, Some(egui::vec2(800.0, 600.0)));
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn rewind_goes_back_to_the_oldest_point_and_spends_a_token() {
+        let mut game = TetrisGame::new(Config::default());
+        game.record_rewind_point();
+        let board = game.board.clone();
+        let piece = game.current_piece.kind;
+        for step in 1..=4 {
+            game.apply_input(InputEvent::HardDrop);
+            game.stats.play_time = REWIND_INTERVAL * step;
+            if step <= 2 {
+                game.record_rewind_point();
+            }
+        }
+        assert_eq!(game.rewind_history.len(), 3);
+        game.rewind();
+        assert_eq!(game.rewind_tokens, REWIND_TOKENS - 1);
+        assert_eq!(game.board, board);
+        assert_eq!(game.current_piece.kind, piece);
+        assert!(game.rewind_history.is_empty());
+        // The replay carries on from before the rewound drops
+        assert!(game.replay.lock().unwrap().events.is_empty());
+
+        game.rewind_tokens = 0;
+        game.record_rewind_point();
+        game.apply_input(InputEvent::HardDrop);
+        game.rewind();
+        assert_ne!(game.board, board);
+
+        let mut daily = TetrisGame::daily(Config::default(), 20_000);
+        daily.record_rewind_point();
+        daily.apply_input(InputEvent::HardDrop);
+        daily.rewind();
+        assert_eq!(daily.rewind_tokens, REWIND_TOKENS);
+        assert!(!daily.rewind_history.is_empty());
+    }
+
+    #[test]
+    fn everyone_gets_the_same_daily_challenge() {
+        let tgm = Config {
+            randomizer: Randomizer::Tgm,
+            starting_garbage: 6,
+            ..Config::default()
+        };
+        let mut games = [
+            TetrisGame::daily(Config::default(), 20_000),
+            TetrisGame::daily(tgm, 20_000),
+        ];
+        assert_eq!(games[0].board, games[1].board);
+        let garbage_rows = games[0]
+            .board
+            .iter()
+            .filter(|row| row.contains(&CellState::Garbage))
+            .count();
+        assert_eq!(garbage_rows, daily::DAILY_GARBAGE as usize);
+        let pieces = games.each_mut().map(|game| {
+            let mut pieces = vec![game.current_piece.kind];
+            pieces.extend((0..14).map(|_| game.bag.next()));
+            pieces
+        });
+        assert_eq!(pieces[0], pieces[1]);
+
+        let tomorrow = TetrisGame::daily(Config::default(), 20_001);
+        assert_ne!(tomorrow.board, games[0].board);
+    }
+
+    #[test]
+    fn spinning_in_place_eventually_locks() {
+        let mut game = TetrisGame::new(Config::default());
+        // Standing upright, so the spins alternate between resting on the floor
+        // and hovering just above it
+        game.current_piece = Tetromino::new(TetrominoType::T);
+        game.rotate_piece(RotationDir::Clockwise);
+        game.reset_lock_delay();
+        game.current_piece.y = game.ghost_y();
+        // Keep gravity out of it
+        game.update_interval = Duration::from_secs(60);
+        game.update();
+        assert!(game.lock_timer.is_some());
+
+        // Each spin comes 400 ms after the last, inside the lock delay
+        let mut spins = 0;
+        while game.stats.pieces == 0 && spins < 10 {
+            game.lock_timer = game.lock_timer.map(|at| at - Duration::from_millis(400));
+            game.apply_input(InputEvent::Rotate);
+            spins += 1;
+            game.update();
+        }
+        // The first spins each buy another lock delay, then the piece locks the
+        // next time it touches down
+        assert_eq!(game.stats.pieces, 1);
+        assert!(spins > MAX_SPINS_IN_PLACE + 1, "locked after {spins} spins");
+    }
+
+    #[test]
+    fn metronome_fades_between_beats() {
+        let mut game = TetrisGame::new(Config::default());
+        game.update_interval = Duration::from_millis(500);
+        let tick = game.last_update;
+        assert_eq!(game.metronome_ticks_per_beat(), 1);
+        assert_eq!(game.metronome_alpha(tick), 1.0);
+        assert!((game.metronome_alpha(tick + Duration::from_millis(250)) - 0.5).abs() < 1e-3);
+
+        // At 50 ms a tick, one beat spans two of them
+        game.update_interval = Duration::from_millis(50);
+        assert_eq!(game.metronome_ticks_per_beat(), 2);
+        game.gravity_ticks = 1;
+        assert!((game.metronome_alpha(tick) - 0.5).abs() < 1e-3);
+        game.gravity_ticks = 2;
+        assert_eq!(game.metronome_alpha(tick), 1.0);
+    }
+
+    #[test]
+    fn lower_stack_holds_the_crown_and_hits_harder() {
+        let mut game = TetrisGame::new(Config::default());
+        game.mode = GameMode::KingOfTheHill;
+        assert_eq!(game.crown(), None);
+        let mut opponent = game.snapshot();
+        opponent.stack_height = 5;
+        game.opponent = Some(opponent);
+        assert_eq!(game.crown(), Some(Crown::Ours));
+        assert_eq!(game.attack_power(), KING_OF_THE_HILL_POWER);
+
+        for row in &mut game.board[BOARD_HEIGHT - 8..] {
+            row[0] = CellState::Garbage;
+        }
+        assert_eq!(game.crown(), Some(Crown::Theirs));
+        assert_eq!(game.attack_power(), 1.0);
+
+        game.mode = GameMode::Marathon;
+        game.board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+        assert_eq!(game.attack_power(), 1.0);
+    }
+
+    #[test]
+    fn clears_remove_full_rows_and_score_them() {
+        // Full rows, rows one cell short of full, then the points under the
+        // Classic and Guideline systems at level 1
+        let cases: [(&[usize], &[usize], u32, u32); 8] = [
+            (&[], &[19], 0, 0),
+            (&[19], &[], 100, 100),
+            (&[19], &[18], 100, 100),
+            (&[10], &[19, 5], 100, 100),
+            (&[18, 19], &[17], 200, 300),
+            (&[3, 11], &[12], 200, 300),
+            (&[0, 9, 19], &[10], 300, 500),
+            (&[16, 17, 18, 19], &[2, 15], 400, 800),
+        ];
+        for (full, partial, classic, guideline) in cases {
+            for (scoring, points) in [
+                (ScoringSystem::Classic, classic),
+                (ScoringSystem::Guideline, guideline),
+            ] {
+                let mut game = TetrisGame::new(Config {
+                    scoring,
+                    ..Config::default()
+                });
+                for &y in full {
+                    game.board[y].fill(CellState::Garbage);
+                }
+                for &y in partial {
+                    game.board[y][1..].fill(CellState::Garbage);
+                }
+                let score = game.score;
+                game.apply_clears(game.detect_clears());
+
+                assert_eq!(game.score - score, points, "{scoring:?} clearing {full:?}");
+                assert_eq!(game.lines_cleared, full.len() as u32);
+                assert_eq!(game.board.len(), BOARD_HEIGHT);
+                assert!(game.board.iter().all(|row| row.len() == BOARD_WIDTH));
+                for row in &game.board[..full.len()] {
+                    assert!(row.iter().all(|cell| *cell == CellState::Empty));
+                }
+                // Each partial row drops by the number of full rows that were below it
+                let mut expected: Vec<usize> = partial
+                    .iter()
+                    .map(|&y| y + full.iter().filter(|&&row| row > y).count())
+                    .collect();
+                expected.sort();
+                let remaining: Vec<usize> = (0..BOARD_HEIGHT)
+                    .filter(|&y| game.board[y].iter().any(|cell| cell.is_filled()))
+                    .collect();
+                assert_eq!(remaining, expected, "clearing {full:?} below {partial:?}");
+                for &y in &remaining {
+                    assert!(!game.board[y][0].is_filled());
+                    assert!(game.board[y][1..].iter().all(|cell| cell.is_filled()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn snapshots_stop_after_the_one_showing_game_over() {
+        let mut game = TetrisGame::new(Config::default());
+        let start = game.last_snapshot;
+        let interval = Duration::from_secs(1) / game.config.snapshot_rate;
+        assert!(!game.snapshot_due(start));
+        assert!(game.snapshot_due(start + interval));
+        game.game_over = true;
+        assert!(game.snapshot_due(start + interval));
+        assert!(!game.snapshot_due(start + interval * 10));
+    }
+
+    #[test]
+    fn a_long_offline_game_keeps_counting_garbage() {
+        let mut game = TetrisGame::new(Config::default());
+        for _ in 0..200 {
+            for row in &mut game.board[BOARD_HEIGHT - 4..] {
+                row.fill(CellState::Garbage);
+            }
+            game.apply_clears(game.detect_clears());
+        }
+        assert_eq!(game.outgoing_garbage, u8::MAX);
+    }
+
+    #[test]
+    fn nothing_is_queued_for_a_screen_reader_that_is_off() {
+        let mut game = TetrisGame::new(Config::default());
+        for row in &mut game.board[16..] {
+            row.fill(CellState::Garbage);
+        }
+        game.apply_clears(game.detect_clears());
+        game.game_over = true;
+        game.finish_game();
+        assert!(game.announcements.is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+// What a lock cleared, as far as the opponent is concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClearType {
+    None,
+    Single,
+    Double,
+    Triple,
+    Tetris,
+    TSpinSingle,
+    TSpinDouble,
+    TSpinTriple,
+    // Emptied the whole board, however many lines that took
+    PerfectClear,
+}
+
+impl ClearType {
+    pub fn from_lines(lines: u32, t_spin: bool, perfect_clear: bool) -> Self {
+        match lines {
+            0 => ClearType::None,
+            _ if perfect_clear => ClearType::PerfectClear,
+            1 if t_spin => ClearType::TSpinSingle,
+            2 if t_spin => ClearType::TSpinDouble,
+            _ if t_spin => ClearType::TSpinTriple,
+            1 => ClearType::Single,
+            2 => ClearType::Double,
+            3 => ClearType::Triple,
+            _ => ClearType::Tetris,
+        }
+    }
+
+    // Tetrises and T-spins keep a back-to-back chain going
+    pub fn is_difficult(self) -> bool {
+        matches!(
+            self,
+            ClearType::Tetris
+                | ClearType::TSpinSingle
+                | ClearType::TSpinDouble
+                | ClearType::TSpinTriple
+        )
+    }
+}
+
+// Extra rows for each clear in a row after the first, guideline style; longer
+// combos send the last entry
+const COMBO_GARBAGE: [u8; 12] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5, 5];
+
+// `combo` is how many clears in a row came right before this one
+pub fn compute_garbage(clear: ClearType, back_to_back: bool, combo: u32) -> u8 {
+    let base = match clear {
+        ClearType::None => return 0,
+        ClearType::Single => 0,
+        ClearType::Double => 1,
+        ClearType::Triple => 2,
+        ClearType::Tetris if back_to_back => 6,
+        ClearType::Tetris => 4,
+        ClearType::TSpinSingle => 2,
+        ClearType::TSpinDouble => 4,
+        ClearType::TSpinTriple => 6,
+        ClearType::PerfectClear => 10,
+    };
+    let back_to_back_bonus =
+        u8::from(back_to_back && clear.is_difficult() && clear != ClearType::Tetris);
+    let combo_bonus = COMBO_GARBAGE[(combo as usize).min(COMBO_GARBAGE.len() - 1)];
+    base + back_to_back_bonus + combo_bonus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attack_table_matches_the_guideline() {
+        let cases = [
+            (ClearType::None, false, 5, 0),
+            (ClearType::Single, false, 0, 0),
+            (ClearType::Double, false, 0, 1),
+            (ClearType::Triple, false, 0, 2),
+            (ClearType::Tetris, false, 0, 4),
+            (ClearType::Tetris, true, 0, 6),
+            (ClearType::TSpinSingle, false, 0, 2),
+            (ClearType::TSpinDouble, false, 0, 4),
+            (ClearType::TSpinTriple, false, 0, 6),
+            (ClearType::TSpinDouble, true, 0, 5),
+            (ClearType::PerfectClear, false, 0, 10),
+            // Back-to-back only rewards the difficult clears
+            (ClearType::Double, true, 0, 1),
+            (ClearType::Single, false, 1, 1),
+            (ClearType::Double, false, 3, 3),
+            (ClearType::Tetris, true, 4, 8),
+            (ClearType::Single, false, 50, 5),
+        ];
+        for (clear, back_to_back, combo, garbage) in cases {
+            assert_eq!(
+                compute_garbage(clear, back_to_back, combo),
+                garbage,
+                "{clear:?}, back to back {back_to_back}, combo {combo}"
+            );
+        }
+    }
+
+    #[test]
+    fn clear_type_follows_the_lines() {
+        assert_eq!(ClearType::from_lines(0, false, true), ClearType::None);
+        assert_eq!(ClearType::from_lines(2, false, false), ClearType::Double);
+        assert_eq!(ClearType::from_lines(4, false, false), ClearType::Tetris);
+        assert_eq!(
+            ClearType::from_lines(1, false, true),
+            ClearType::PerfectClear
+        );
+        assert_eq!(ClearType::from_lines(0, true, false), ClearType::None);
+        assert_eq!(
+            ClearType::from_lines(2, true, false),
+            ClearType::TSpinDouble
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// How fast pieces fall as the level goes up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GravityCurve {
+    Linear,
+    Exponential,
+    Step,
+    Classic,
+}
+
+impl GravityCurve {
+    pub const ALL: [GravityCurve; 4] = [
+        GravityCurve::Linear,
+        GravityCurve::Exponential,
+        GravityCurve::Step,
+        GravityCurve::Classic,
+    ];
+}
+
+// What makes the pieces speed up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedMode {
+    // Each level follows the gravity curve
+    Level,
+    // Every 1000 points take 5 ms off the level 1 speed, whatever the level
+    Score,
+}
+
+impl SpeedMode {
+    pub const ALL: [SpeedMode; 2] = [SpeedMode::Level, SpeedMode::Score];
+}
+
+const SCORE_STEP: u32 = 1000;
+const SCORE_SPEEDUP: Duration = Duration::from_millis(5);
+const SCORE_MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+const NES_FPS: f32 = 60.0988;
+const GAME_BOY_FPS: f32 = 59.73;
+
+// Frames per row on NES levels 0 to 28; level 29 and up drop a row every frame
+const NES_FRAMES: [u32; 29] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+
+// Frames per row on Game Boy levels 0 to 20, which is as fast as it gets
+const GAME_BOY_FRAMES: [u32; 21] = [
+    53, 49, 45, 41, 37, 33, 28, 22, 17, 11, 10, 9, 8, 7, 6, 6, 5, 5, 4, 4, 3,
+];
+
+// Our levels start at 1, the consoles' at 0
+pub fn gravity_interval(level: u32, curve: GravityCurve) -> Duration {
+    let index = level.saturating_sub(1) as usize;
+    let seconds = match curve {
+        // 0.75 s at level 1 down to 0.05 s at level 15
+        GravityCurve::Linear => (0.75 - index as f32 * 0.05).max(0.05),
+        // The guideline formula, which stops speeding up at level 20
+        GravityCurve::Exponential => {
+            let n = index.min(19) as f32;
+            (0.8 - n * 0.007).powf(n)
+        }
+        GravityCurve::Step => NES_FRAMES.get(index).copied().unwrap_or(1) as f32 / NES_FPS,
+        GravityCurve::Classic => {
+            GAME_BOY_FRAMES[index.min(GAME_BOY_FRAMES.len() - 1)] as f32 / GAME_BOY_FPS
+        }
+    };
+    Duration::from_secs_f32(seconds)
+}
+
+pub fn score_interval(score: u32, curve: GravityCurve) -> Duration {
+    let speedup = SCORE_SPEEDUP * (score / SCORE_STEP);
+    gravity_interval(1, curve)
+        .saturating_sub(speedup)
+        .max(SCORE_MIN_INTERVAL)
+}
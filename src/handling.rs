@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+// How held keys repeat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handling {
+    // Holding left or right waits this long before it starts repeating
+    pub das_delay_ms: u32,
+    // Then moves again this often; 0 goes straight to the wall
+    pub arr_ms: u32,
+    // Soft drop falls this many times faster than gravity
+    pub sdf: u32,
+    // Holding soft drop waits this long after the first row before repeating
+    pub dfr_ms: u32,
+}
+
+impl Handling {
+    pub fn das(self) -> Duration {
+        Duration::from_millis(self.das_delay_ms.into())
+    }
+
+    pub fn arr(self) -> Duration {
+        Duration::from_millis(self.arr_ms.into())
+    }
+
+    pub fn dfr(self) -> Duration {
+        Duration::from_millis(self.dfr_ms.into())
+    }
+
+    pub fn soft_drop_interval(self, gravity: Duration) -> Duration {
+        gravity / self.sdf.max(1)
+    }
+}
+
+// Handling copied from other games, as near as their frame counts allow at 60 fps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandlingPreset {
+    #[default]
+    Guideline,
+    // The NES: a long wait, then a slow repeat
+    Classic,
+    TetrisFriends,
+    TetIO,
+    // Whatever the fields were last set to
+    Custom,
+}
+
+impl HandlingPreset {
+    pub const ALL: [HandlingPreset; 5] = [
+        HandlingPreset::Guideline,
+        HandlingPreset::Classic,
+        HandlingPreset::TetrisFriends,
+        HandlingPreset::TetIO,
+        HandlingPreset::Custom,
+    ];
+
+    // None for Custom, which leaves the fields alone
+    pub fn handling(self) -> Option<Handling> {
+        let (das_delay_ms, arr_ms, sdf, dfr_ms) = match self {
+            HandlingPreset::Guideline => (167, 33, 20, 0),
+            HandlingPreset::Classic => (267, 100, 20, 50),
+            HandlingPreset::TetrisFriends => (250, 50, 10, 0),
+            HandlingPreset::TetIO => (167, 33, 6, 17),
+            HandlingPreset::Custom => return None,
+        };
+        Some(Handling {
+            das_delay_ms,
+            arr_ms,
+            sdf,
+            dfr_ms,
+        })
+    }
+}
+
+// A movement key since it went down, and how many moves it has made
+#[derive(Clone, Copy, Debug)]
+pub struct HeldKey {
+    pub since: Instant,
+    pub moves: u32,
+}
+
+impl HeldKey {
+    pub fn new(now: Instant) -> Self {
+        HeldKey {
+            since: now,
+            moves: 0,
+        }
+    }
+}
+
+// How many moves a key held for `held` should have made by now, counting the
+// one on the press. It repeats every `interval` once `delay` is up, and an
+// interval of zero repeats as often as it's asked
+pub fn moves_due(held: Duration, delay: Duration, interval: Duration) -> u32 {
+    if held < delay {
+        return 1;
+    }
+    if interval.is_zero() {
+        return u32::MAX;
+    }
+    let repeats = (held - delay).as_nanos() / interval.as_nanos();
+    2 + repeats.min(u32::MAX as u128 - 2) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_keys_wait_then_repeat() {
+        let ms = Duration::from_millis;
+        let (das, arr) = (ms(167), ms(33));
+        assert_eq!(moves_due(ms(0), das, arr), 1);
+        assert_eq!(moves_due(ms(166), das, arr), 1);
+        assert_eq!(moves_due(ms(167), das, arr), 2);
+        assert_eq!(moves_due(ms(199), das, arr), 2);
+        assert_eq!(moves_due(ms(200), das, arr), 3);
+        assert_eq!(moves_due(ms(200), das, Duration::ZERO), u32::MAX);
+    }
+
+    #[test]
+    fn every_preset_but_custom_sets_the_fields() {
+        for preset in HandlingPreset::ALL {
+            assert_eq!(
+                preset.handling().is_some(),
+                preset != HandlingPreset::Custom
+            );
+        }
+        let guideline = HandlingPreset::Guideline.handling().unwrap();
+        assert_eq!(
+            guideline.soft_drop_interval(Duration::from_millis(1000)),
+            Duration::from_millis(50)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use crate::bag::{PieceBag, Randomizer};
+use crate::config::Config;
+use crate::{CellState, TetrisGame, Tetromino, TetrominoType, BOARD_HEIGHT};
+
+// Strong bots practically never top out, so each benchmark game stops here
+const BENCHMARK_PIECE_LIMIT: u32 = 500;
+
+// Plays a game with the bot as fast as possible, without a window
+pub struct HeadlessDriver {
+    pub(crate) game: TetrisGame,
+    pub(crate) pieces: u32,
+}
+
+impl HeadlessDriver {
+    pub(crate) fn new(config: Config) -> Self {
+        let mut game = TetrisGame::new(config);
+        game.ai_mode = true;
+        HeadlessDriver { game, pieces: 0 }
+    }
+
+    // A default game dealt from a seeded seven-bag, the same every time
+    pub fn seeded(seed: u64) -> Self {
+        let mut driver = HeadlessDriver::new(Config::default());
+        driver.game.bag = PieceBag::seeded(Randomizer::SevenBag, seed);
+        // The first piece came from the unseeded bag
+        driver.game.current_piece = Tetromino::new(driver.game.bag.next());
+        driver
+    }
+
+    // Makes one bot decision, returning false once the game is over
+    pub fn step(&mut self) -> bool {
+        if self.game.game_over {
+            return false;
+        }
+        self.game.play_bot_move();
+        self.pieces += 1;
+        !self.game.game_over
+    }
+
+    pub fn run(&mut self, max_pieces: u32) {
+        while self.pieces < max_pieces && self.step() {}
+    }
+
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    pub fn lines_cleared(&self) -> u32 {
+        self.game.lines_cleared
+    }
+
+    pub fn score(&self) -> u32 {
+        self.game.score
+    }
+
+    pub fn level(&self) -> u32 {
+        self.game.level
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.game.game_over
+    }
+}
+
+// A vertical I locking into rows of nine, for benches/lock_bench.rs. The rows'
+// gap is in column 0, under the I, so every lock clears four lines; without
+// clears a second gap in column 9 keeps the rows open
+pub struct LockBench {
+    game: TetrisGame,
+    board: Vec<Vec<CellState>>,
+}
+
+impl LockBench {
+    pub fn new(clears: bool) -> Self {
+        let gaps: &[usize] = if clears { &[0] } else { &[0, 9] };
+        let mut game = TetrisGame::new(Config {
+            reduced_motion: true,
+            ..Config::default()
+        });
+        for row in &mut game.board[4..] {
+            row.fill(CellState::Garbage);
+            for &x in gaps {
+                row[x] = CellState::Empty;
+            }
+        }
+        let board = game.board.clone();
+        LockBench { game, board }
+    }
+
+    // Puts the rows back and locks the I into them, returning the lines it
+    // cleared. clone_from reuses the rows, so the reset costs little next to
+    // the lock
+    pub fn lock(&mut self) -> u32 {
+        let game = &mut self.game;
+        let lines_before = game.lines_cleared;
+        game.board.clone_from(&self.board);
+        game.current_piece = Tetromino::new(TetrominoType::I);
+        game.current_piece.shape = vec![vec![true]; 4];
+        game.current_piece.x = 0;
+        game.current_piece.y = BOARD_HEIGHT as isize - 4;
+        game.lock_piece();
+        game.lines_cleared - lines_before
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    pub games: usize,
+    pub decisions_per_second: f64,
+    pub average_score: f64,
+    pub average_lines: f64,
+}
+
+// `progress` counts finished games so the UI can show how far along it is
+pub(crate) fn benchmark(config: &Config, games: usize, progress: &AtomicUsize) -> BenchmarkResult {
+    let start = Instant::now();
+    let mut decisions = 0;
+    let mut total_score = 0;
+    let mut total_lines = 0;
+    for _ in 0..games {
+        let mut driver = HeadlessDriver::new(config.clone());
+        driver.run(BENCHMARK_PIECE_LIMIT);
+        decisions += driver.pieces as u64;
+        total_score += driver.game.score as u64;
+        total_lines += driver.game.lines_cleared as u64;
+        progress.fetch_add(1, Ordering::Relaxed);
+    }
+    BenchmarkResult {
+        games,
+        decisions_per_second: decisions as f64 / start.elapsed().as_secs_f64(),
+        average_score: total_score as f64 / games as f64,
+        average_lines: total_lines as f64 / games as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_lock_bench_clears_only_when_asked() {
+        let mut with = LockBench::new(true);
+        let mut without = LockBench::new(false);
+        for _ in 0..3 {
+            assert_eq!(with.lock(), 4);
+            assert_eq!(without.lock(), 0);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::daily::{self, DailyBest};
+
+// Games kept in the table; a game that doesn't make the top scores is dropped
+pub const MAX_ENTRIES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    #[serde(default)]
+    pub name: String,
+    // The GameMode it was played in
+    #[serde(default)]
+    pub mode: String,
+    // Days since the Unix epoch, as daily::today counts them
+    #[serde(default)]
+    pub day: u64,
+    pub score: u32,
+    pub level: u32,
+    pub lines: u32,
+    // Share of placed cells that didn't bury a hole, 0 to 1
+    pub efficiency: f32,
+    pub lines_per_minute: f32,
+    #[serde(default)]
+    pub pieces_per_second: f32,
+    #[serde(default)]
+    pub finesse_errors: u32,
+}
+
+// Which column the high score panel is sorted on, best first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortBy {
+    #[default]
+    Score,
+    Level,
+    Lines,
+    Efficiency,
+    Lpm,
+    // Fewest first
+    Finesse,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 6] = [
+        SortBy::Score,
+        SortBy::Level,
+        SortBy::Lines,
+        SortBy::Efficiency,
+        SortBy::Lpm,
+        SortBy::Finesse,
+    ];
+
+    // The header's locale key
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Score => "column_score",
+            SortBy::Level => "level_reached",
+            SortBy::Lines => "lines",
+            SortBy::Efficiency => "stats_efficiency",
+            SortBy::Lpm => "column_lpm",
+            SortBy::Finesse => "column_finesse",
+        }
+    }
+
+    // Higher is better
+    fn key(self, entry: &HighScoreEntry) -> f32 {
+        match self {
+            SortBy::Score => entry.score as f32,
+            SortBy::Level => entry.level as f32,
+            SortBy::Lines => entry.lines as f32,
+            SortBy::Efficiency => entry.efficiency,
+            SortBy::Lpm => entry.lines_per_minute,
+            SortBy::Finesse => -(entry.finesse_errors as f32),
+        }
+    }
+}
+
+// The best finished games by score, and today's best daily challenge, saved
+// with the config
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    entries: Vec<HighScoreEntry>,
+    #[serde(default)]
+    pub daily_best: DailyBest,
+}
+
+impl HighScoreTable {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn record(&mut self, entry: HighScoreEntry) {
+        let at = self
+            .entries
+            .partition_point(|kept| kept.score >= entry.score);
+        self.entries.insert(at, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    // Best score first. The version line starts with `#` so a later format can be
+    // told apart; spreadsheets read it as one cell above the header
+    pub fn export_csv(&self, path: &Path) -> anyhow::Result<()> {
+        let mut csv = String::from("# tetris-rs scores v1\n");
+        csv += "name,score,level,lines,mode,date,pps,lpm,efficiency\n";
+        for entry in &self.entries {
+            csv += &format!(
+                "{},{},{},{},{},{},{:.2},{:.2},{:.3}\n",
+                csv_field(&entry.name),
+                entry.score,
+                entry.level,
+                entry.lines,
+                csv_field(&entry.mode),
+                daily::date(entry.day),
+                entry.pieces_per_second,
+                entry.lines_per_minute,
+                entry.efficiency,
+            );
+        }
+        fs::write(path, csv).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    // Best first; ties keep the order of their scores
+    pub fn sorted_by(&self, criterion: SortBy) -> Vec<&HighScoreEntry> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|a, b| criterion.key(b).total_cmp(&criterion.key(a)));
+        entries
+    }
+}
+
+// Quoted only when it has to be, with quotes inside doubled
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, lines: u32, efficiency: f32) -> HighScoreEntry {
+        HighScoreEntry {
+            name: "player".to_string(),
+            mode: "Marathon".to_string(),
+            day: 20_740,
+            score,
+            level: 1 + lines / 10,
+            lines,
+            efficiency,
+            lines_per_minute: lines as f32 / 2.0,
+            pieces_per_second: 1.5,
+            finesse_errors: lines % 4,
+        }
+    }
+
+    #[test]
+    fn keeps_the_top_scores_and_sorts_by_any_column() {
+        let mut table = HighScoreTable::default();
+        for score in 0..MAX_ENTRIES as u32 + 2 {
+            table.record(entry(score * 100, 30 - score, score as f32 / 20.0));
+        }
+        let by_score = table.sorted_by(SortBy::Score);
+        assert_eq!(by_score.len(), MAX_ENTRIES);
+        assert_eq!(by_score[0].score, (MAX_ENTRIES as u32 + 1) * 100);
+        assert_eq!(by_score.last().unwrap().score, 200);
+
+        let by_lines = table.sorted_by(SortBy::Lines);
+        assert_eq!(by_lines[0].score, 200);
+        assert!(by_lines
+            .windows(2)
+            .all(|pair| pair[0].lines >= pair[1].lines));
+        let by_efficiency = table.sorted_by(SortBy::Efficiency);
+        assert_eq!(by_efficiency, by_score);
+        let by_finesse = table.sorted_by(SortBy::Finesse);
+        assert_eq!(by_finesse[0].finesse_errors, 0);
+        assert!(by_finesse
+            .windows(2)
+            .all(|pair| pair[0].finesse_errors <= pair[1].finesse_errors));
+        // Level ties stay in score order
+        let by_level = table.sorted_by(SortBy::Level);
+        assert!(by_level
+            .windows(2)
+            .all(|pair| pair[0].level > pair[1].level || pair[0].score > pair[1].score));
+    }
+
+    #[test]
+    fn exports_a_csv_with_a_version_line() {
+        let mut table = HighScoreTable::default();
+        table.record(entry(300, 12, 0.9));
+        table.record(HighScoreEntry {
+            name: "Sam \"the, best\"".to_string(),
+            ..entry(500, 20, 0.75)
+        });
+        let path =
+            std::env::temp_dir().join(format!("tetris-rs-scores-{}.csv", std::process::id()));
+        table.export_csv(&path).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            csv,
+            "# tetris-rs scores v1\n\
+             name,score,level,lines,mode,date,pps,lpm,efficiency\n\
+             \"Sam \"\"the, best\"\"\",500,3,20,Marathon,2026-10-14,1.50,10.00,0.750\n\
+             player,300,2,12,Marathon,2026-10-14,1.50,6.00,0.900\n"
+        );
+    }
+}
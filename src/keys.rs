@@ -0,0 +1,126 @@
+use eframe::egui;
+use egui::Key;
+use serde::{Deserialize, Serialize};
+
+// The keys for each action in a one-player game. Any of an action's keys does it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_left: Vec<Key>,
+    pub move_right: Vec<Key>,
+    pub soft_drop: Vec<Key>,
+    pub hard_drop: Vec<Key>,
+    pub rotate: Vec<Key>,
+    pub rotate_reverse: Vec<Key>,
+    pub hold: Vec<Key>,
+    pub pause: Vec<Key>,
+    pub rewind: Vec<Key>,
+    pub stats: Vec<Key>,
+    pub placements: Vec<Key>,
+    pub grid: Vec<Key>,
+    pub debug: Vec<Key>,
+    pub isometric: Vec<Key>,
+    pub fullscreen: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_left: vec![Key::ArrowLeft],
+            move_right: vec![Key::ArrowRight],
+            soft_drop: vec![Key::ArrowDown],
+            hard_drop: vec![Key::Space],
+            rotate: vec![Key::ArrowUp, Key::X],
+            rotate_reverse: vec![Key::Z],
+            hold: vec![Key::C],
+            pause: vec![Key::P],
+            rewind: vec![Key::R],
+            stats: vec![Key::S],
+            placements: vec![Key::Tab],
+            grid: vec![Key::G],
+            debug: vec![Key::F2],
+            isometric: vec![Key::F4],
+            fullscreen: vec![Key::F11],
+        }
+    }
+}
+
+impl KeyBindings {
+    // (keys, locale key of what they do) for the controls overlay. `?` isn't
+    // a key egui knows, so the overlay's own entry can't be rebound
+    pub fn controls(&self) -> Vec<(String, &'static str)> {
+        let shift = format!("{} {}", label(&self.move_left), label(&self.move_right));
+        let mut controls = vec![(shift, "control_move")];
+        controls.extend(
+            [
+                (&self.soft_drop, "control_soft_drop"),
+                (&self.hard_drop, "control_hard_drop"),
+                (&self.rotate, "control_rotate"),
+                (&self.rotate_reverse, "control_rotate_reverse"),
+                (&self.hold, "control_hold"),
+                (&self.pause, "control_pause"),
+                (&self.rewind, "control_rewind"),
+                (&self.stats, "control_stats"),
+                (&self.placements, "control_placements"),
+                (&self.grid, "control_grid"),
+                (&self.debug, "control_debug"),
+                (&self.isometric, "control_isometric"),
+                (&self.fullscreen, "control_fullscreen"),
+            ]
+            .map(|(keys, action)| (label(keys), action)),
+        );
+        controls.push(("?".to_string(), "control_controls"));
+        controls
+    }
+}
+
+// The keys' names joined by slashes, with the arrows drawn as arrows
+fn label(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|key| match key {
+            Key::ArrowLeft => "←",
+            Key::ArrowRight => "→",
+            Key::ArrowUp => "↑",
+            Key::ArrowDown => "↓",
+            _ => key.name(),
+        })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+// One of `keys` going down this frame, not the system's key repeat; the
+// handling settings decide how held keys repeat
+pub fn first_press(input: &egui::InputState, keys: &[Key]) -> bool {
+    input.events.iter().any(|event| {
+        matches!(
+            event,
+            egui::Event::Key { key, pressed: true, repeat: false, .. } if keys.contains(key)
+        )
+    })
+}
+
+pub fn pressed(input: &egui::InputState, keys: &[Key]) -> bool {
+    keys.iter().any(|&key| input.key_pressed(key))
+}
+
+pub fn down(input: &egui::InputState, keys: &[Key]) -> bool {
+    keys.iter().any(|&key| input.key_down(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_survive_the_config_file_and_list_every_key() {
+        let bindings = KeyBindings::default();
+        let saved = toml::to_string(&bindings).unwrap();
+        assert_eq!(toml::from_str::<KeyBindings>(&saved).unwrap(), bindings);
+
+        let controls = bindings.controls();
+        assert_eq!(controls[0].0, "← →");
+        assert!(controls.contains(&("↑ / X".to_string(), "control_rotate")));
+        assert!(controls.contains(&("Z".to_string(), "control_rotate_reverse")));
+        assert_eq!(controls.last().unwrap().0, "?");
+    }
+}
@@ -0,0 +1,63 @@
+mod ai;
+mod bag;
+mod challenge;
+mod cli;
+mod config;
+mod daily;
+mod end_screen;
+mod events;
+mod finesse;
+mod four_player;
+mod game;
+mod garbage;
+mod gravity;
+mod handling;
+pub mod headless;
+mod high_scores;
+mod keys;
+mod lobby;
+mod locale;
+mod net;
+mod openers;
+mod replay;
+mod rotation;
+mod scoring;
+#[cfg(feature = "sound")]
+mod sound;
+mod spectator;
+mod strict;
+mod theme;
+mod tutorial;
+mod ui;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+use clap::Parser;
+
+use cli::{Args, Command};
+pub(crate) use game::{
+    rotated, CellState, GameMode, InputEvent, TetrisGame, Tetromino, TetrominoType, BOARD_HEIGHT,
+    BOARD_WIDTH, LOCK_DELAY, MAX_SPINS_IN_PLACE,
+};
+use replay::{Replay, ReplayViewer};
+use ui::{open_window, play};
+
+pub fn run() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let log_error = args.init_logging().err().map(|err| format!("{err:#}"));
+    match args.command() {
+        Command::Play(play_args) => play(play_args, log_error),
+        Command::Benchmark { games, ai } => cli::benchmark(games, ai),
+        Command::Replay { file } => {
+            let replay = Replay::load(&file)?;
+            open_window("Tetris replay", move |_| {
+                Box::new(ReplayViewer::new(replay))
+            })
+        }
+        Command::ValidateReplay { file } => cli::validate_replay(&file),
+        Command::PrintBoard { seed, moves } => {
+            print!("{}", cli::print_board(seed, moves));
+            Ok(())
+        }
+    }
+}
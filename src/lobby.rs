@@ -0,0 +1,223 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use egui::Color32;
+
+use crate::locale::{locale, Language};
+use crate::net::{NetworkLink, NetworkMode, NetworkStatus, Packet};
+
+const COUNTDOWN: Duration = Duration::from_secs(3);
+const DEFAULT_PORT: u16 = 7777;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LobbyTab {
+    Client,
+    Server,
+}
+
+pub enum LobbyOutcome {
+    // The countdown finished; play against whoever is on the other end
+    Start(Arc<NetworkLink>),
+    // Back to a local game
+    Leave,
+}
+
+// Where two players find each other before an online game
+#[derive(Clone)]
+pub struct Lobby {
+    tab: LobbyTab,
+    pub username: String,
+    address: String,
+    port: String,
+    link: Option<Arc<NetworkLink>>,
+    hello_sent: bool,
+    opponent: Option<String>,
+    ready: bool,
+    opponent_ready: bool,
+    countdown: Option<Instant>,
+    leave: bool,
+    language: Language,
+}
+
+impl Lobby {
+    pub fn new(username: String, language: Language) -> Self {
+        Lobby {
+            tab: LobbyTab::Client,
+            username: if username.is_empty() {
+                locale(language).get("player").to_string()
+            } else {
+                username
+            },
+            address: format!("127.0.0.1:{DEFAULT_PORT}"),
+            port: DEFAULT_PORT.to_string(),
+            link: None,
+            hello_sent: false,
+            opponent: None,
+            ready: false,
+            opponent_ready: false,
+            countdown: None,
+            leave: false,
+            language,
+        }
+    }
+
+    // Skips the tabs and starts connecting straight away
+    pub fn connecting(username: String, language: Language, mode: NetworkMode) -> Self {
+        let mut lobby = Lobby::new(username, language);
+        match &mode {
+            NetworkMode::Server { port } => {
+                lobby.tab = LobbyTab::Server;
+                lobby.port = port.to_string();
+            }
+            NetworkMode::Client { address } | NetworkMode::Spectate { address } => {
+                lobby.address = address.clone()
+            }
+        }
+        lobby.link = Some(Arc::new(NetworkLink::start(mode)));
+        lobby
+    }
+
+    pub fn outcome(&self) -> Option<LobbyOutcome> {
+        if self.leave {
+            return Some(LobbyOutcome::Leave);
+        }
+        let started = self.countdown?;
+        (started.elapsed() >= COUNTDOWN)
+            .then(|| self.link.clone().map(LobbyOutcome::Start))
+            .flatten()
+    }
+
+    fn poll(&mut self) {
+        let Some(link) = &self.link else {
+            return;
+        };
+        for packet in link.poll() {
+            match packet {
+                Packet::Hello { username } => self.opponent = Some(username),
+                Packet::Ready => self.opponent_ready = true,
+                Packet::Game(_) | Packet::Watch | Packet::Snapshot(_) => {}
+            }
+        }
+        if link.status() == NetworkStatus::Connected && !self.hello_sent {
+            link.send_packet(Packet::Hello {
+                username: self.username.clone(),
+            });
+            self.hello_sent = true;
+        }
+        if self.ready && self.opponent_ready && self.countdown.is_none() {
+            self.countdown = Some(Instant::now());
+        }
+    }
+
+    fn connection_ui(&mut self, ui: &mut egui::Ui) {
+        let t = locale(self.language);
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tab, LobbyTab::Client, t.get("client"));
+            ui.selectable_value(&mut self.tab, LobbyTab::Server, t.get("server"));
+        });
+        ui.horizontal(|ui| {
+            ui.label(t.get("username"));
+            ui.text_edit_singleline(&mut self.username);
+        });
+        match self.tab {
+            LobbyTab::Client => {
+                ui.horizontal(|ui| {
+                    ui.label(t.get("server_address"));
+                    ui.text_edit_singleline(&mut self.address);
+                });
+                if ui.button(t.get("connect")).clicked() {
+                    let mode = NetworkMode::Client {
+                        address: self.address.clone(),
+                    };
+                    self.link = Some(Arc::new(NetworkLink::start(mode)));
+                }
+            }
+            LobbyTab::Server => {
+                ui.horizontal(|ui| {
+                    ui.label(t.get("listen_port"));
+                    ui.text_edit_singleline(&mut self.port);
+                });
+                match self.port.parse() {
+                    Ok(port) => {
+                        if ui.button(t.get("host")).clicked() {
+                            let mode = NetworkMode::Server { port };
+                            self.link = Some(Arc::new(NetworkLink::start(mode)));
+                        }
+                    }
+                    Err(_) => {
+                        ui.colored_label(Color32::RED, t.get("invalid_port"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn waiting_ui(&mut self, ui: &mut egui::Ui, status: NetworkStatus) {
+        let t = locale(self.language);
+        match status {
+            NetworkStatus::Connecting => {
+                ui.label(match self.tab {
+                    LobbyTab::Client => t.get("connecting"),
+                    LobbyTab::Server => t.get("waiting_for_join"),
+                });
+            }
+            NetworkStatus::Connected => {
+                let opponent = self
+                    .opponent
+                    .as_deref()
+                    .unwrap_or(t.get("unknown_opponent"));
+                ui.label(t.fill("connected_to", &[&opponent]));
+                ui.label(if self.opponent_ready {
+                    t.get("opponent_ready")
+                } else {
+                    t.get("opponent_not_ready")
+                });
+                match self.countdown {
+                    Some(started) => {
+                        let left = COUNTDOWN.saturating_sub(started.elapsed());
+                        ui.heading(t.fill("starting_in", &[&(left.as_secs() + 1)]));
+                    }
+                    None if self.ready => {
+                        ui.label(t.get("waiting_for_opponent"));
+                    }
+                    None => {
+                        if ui.button(t.get("ready")).clicked() {
+                            self.ready = true;
+                            if let Some(link) = &self.link {
+                                link.send_packet(Packet::Ready);
+                            }
+                        }
+                    }
+                }
+            }
+            NetworkStatus::Disconnected(reason) => {
+                ui.heading(t.get("connection_lost"));
+                ui.label(reason);
+                if ui.button(t.get("return_to_menu")).clicked() {
+                    self.leave = true;
+                }
+            }
+        }
+    }
+}
+
+impl eframe::App for Lobby {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll();
+        let t = locale(self.language);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(t.get("online_lobby"));
+            match self.link.as_ref().map(|link| link.status()) {
+                Some(status) => self.waiting_ui(ui, status),
+                None => {
+                    self.connection_ui(ui);
+                    if ui.button(t.get("back")).clicked() {
+                        self.leave = true;
+                    }
+                }
+            }
+        });
+        ctx.request_repaint();
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Japanese];
+
+    // Shown in the language picker in the language itself
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Japanese => "日本語",
+        }
+    }
+}
+
+// UI strings for one language, read from locales/<lang>.toml
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Locale {
+    fn parse(text: &str) -> Self {
+        Locale {
+            // coverage: ignore
+            strings: toml::from_str(text).expect("locale files are valid"),
+        }
+    }
+
+    // Falls back to English, then to the key itself, so a missing translation
+    // shows up as text rather than a crash
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| english().strings.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    // `get` with each `{}` in the text replaced by the next argument
+    pub fn fill(&self, key: &str, args: &[&dyn std::fmt::Display]) -> String {
+        let mut args = args.iter();
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+fn english() -> &'static Locale {
+    static EN: OnceLock<Locale> = OnceLock::new();
+    EN.get_or_init(|| Locale::parse(include_str!("../locales/en.toml")))
+}
+
+pub fn locale(language: Language) -> &'static Locale {
+    static JA: OnceLock<Locale> = OnceLock::new();
+    match language {
+        Language::English => english(),
+        Language::Japanese => JA.get_or_init(|| Locale::parse(include_str!("../locales/ja.toml"))),
+    }
+}
+
+// egui's bundled fonts have no Japanese glyphs, so borrow a system CJK font
+// when one is installed
+const CJK_FONTS: [&str; 4] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+];
+
+pub fn install_fallback_fonts(ctx: &egui::Context) {
+    let Some(data) = CJK_FONTS.iter().find_map(|path| std::fs::read(path).ok()) else {
+        return;
+    };
+    let mut fonts = egui::FontDefinitions::default();
+    fonts
+        .font_data
+        .insert("cjk".to_string(), egui::FontData::from_owned(data));
+    for family in fonts.families.values_mut() {
+        family.push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_language_has_every_english_key() {
+        for language in Language::ALL {
+            let strings = &locale(language).strings;
+            for key in english().strings.keys() {
+                assert!(strings.contains_key(key), "{language:?} is missing {key}");
+            }
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::spectator::BoardSnapshot;
+use crate::InputEvent;
+
+// The most games one spectator can watch at once
+pub const MAX_SPECTATED: usize = 2;
+
+#[derive(Clone, Debug)]
+pub enum NetworkMode {
+    // Waits for the opponent to connect on this port
+    Server { port: u16 },
+    Client { address: String },
+    // Watches the game hosted at this address without playing
+    Spectate { address: String },
+}
+
+// One line of JSON on the wire per input the sender applied to its own board
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct NetworkMessage {
+    pub event: InputEvent,
+    pub timestamp_us: u64,
+    // Garbage rows the receiver should add to its board
+    pub garbage_lines: u8,
+}
+
+// Everything sent over the connection, one JSON line each
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Packet {
+    // Sent by both sides as soon as they connect
+    Hello { username: String },
+    // The sender is ready for the countdown to start
+    Ready,
+    Game(NetworkMessage),
+    // Sent by a spectator in place of Hello, so the host doesn't play against it
+    Watch,
+    // The sender's board, which the host also passes on to its spectators
+    Snapshot(BoardSnapshot),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkStatus {
+    Connecting,
+    Connected,
+    Disconnected(String),
+}
+
+enum LinkEvent {
+    Connected,
+    Packet(Packet),
+    Closed(String),
+}
+
+// The game's end of the connection; the socket itself lives on a background
+// thread running a tokio runtime
+pub struct NetworkLink {
+    outgoing: Sender<Packet>,
+    incoming: Mutex<Receiver<LinkEvent>>,
+    status: Mutex<NetworkStatus>,
+}
+
+impl NetworkLink {
+    pub fn start(mode: NetworkMode) -> Self {
+        let (outgoing_tx, outgoing_rx) = mpsc::channel();
+        let (incoming_tx, incoming_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    let _ = incoming_tx.send(LinkEvent::Closed(err.to_string()));
+                    return;
+                }
+            };
+            let reason = match runtime.block_on(run(mode, outgoing_rx, incoming_tx.clone())) {
+                Ok(()) => "opponent left".to_string(),
+                Err(err) => err.to_string(),
+            };
+            let _ = incoming_tx.send(LinkEvent::Closed(reason));
+            runtime.shutdown_background();
+        });
+        NetworkLink {
+            outgoing: outgoing_tx,
+            incoming: Mutex::new(incoming_rx),
+            status: Mutex::new(NetworkStatus::Connecting),
+        }
+    }
+
+    pub fn status(&self) -> NetworkStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    // Dropped unless connected, so nothing piles up while waiting for the opponent
+    pub fn send_packet(&self, packet: Packet) {
+        if self.status() == NetworkStatus::Connected {
+            let _ = self.outgoing.send(packet);
+        }
+    }
+
+    pub fn send(&self, event: InputEvent, garbage_lines: u8) {
+        let timestamp_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64);
+        self.send_packet(Packet::Game(NetworkMessage {
+            event,
+            timestamp_us,
+            garbage_lines,
+        }));
+    }
+
+    // Packets received since the last poll
+    pub fn poll(&self) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        let mut status = self.status.lock().unwrap();
+        for event in self.incoming.lock().unwrap().try_iter() {
+            match event {
+                LinkEvent::Connected => *status = NetworkStatus::Connected,
+                LinkEvent::Packet(packet) => packets.push(packet),
+                LinkEvent::Closed(reason) => *status = NetworkStatus::Disconnected(reason),
+            }
+        }
+        packets
+    }
+}
+
+async fn run(
+    mode: NetworkMode,
+    outgoing: Receiver<Packet>,
+    incoming: Sender<LinkEvent>,
+) -> io::Result<()> {
+    let spectators = Arc::new(Mutex::new(Vec::new()));
+    let hosting = matches!(mode, NetworkMode::Server { .. });
+    let (mut lines, mut writer, first_packet) = match mode {
+        NetworkMode::Server { port } => {
+            let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+            // Spectators can turn up before the opponent, so whoever connects
+            // is only the opponent if the first thing it sends isn't Watch
+            let opponent = loop {
+                let (reader, writer) = listener.accept().await?.0.into_split();
+                let (lines, first_packet) = read_first(reader).await?;
+                match first_packet {
+                    Some(Packet::Watch) => add_spectator(&spectators, writer),
+                    _ => break (lines, writer, first_packet),
+                }
+            };
+            tokio::spawn(accept_spectators(listener, spectators.clone()));
+            opponent
+        }
+        NetworkMode::Client { address } => {
+            let (reader, writer) = TcpStream::connect(address).await?.into_split();
+            (BufReader::new(reader).lines(), writer, None)
+        }
+        NetworkMode::Spectate { address } => {
+            let (reader, mut writer) = TcpStream::connect(address).await?.into_split();
+            writer
+                .write_all(json_line(&Packet::Watch)?.as_bytes())
+                .await?;
+            (BufReader::new(reader).lines(), writer, None)
+        }
+    };
+    let _ = incoming.send(LinkEvent::Connected);
+    if let Some(packet) = first_packet {
+        let _ = incoming.send(LinkEvent::Packet(packet));
+    }
+
+    // The game side uses a std channel, so its receiving end has to block
+    let handle = tokio::runtime::Handle::current();
+    let relay = spectators.clone();
+    tokio::task::spawn_blocking(move || -> io::Result<()> {
+        while let Ok(packet) = outgoing.recv() {
+            let line = json_line(&packet)?;
+            if hosting && matches!(packet, Packet::Snapshot(_)) {
+                broadcast(&relay, &line);
+            }
+            handle.block_on(writer.write_all(line.as_bytes()))?;
+        }
+        Ok(())
+    });
+
+    while let Some(line) = lines.next_line().await? {
+        if let Ok(mut packet) = serde_json::from_str(&line) {
+            // Only the host passes boards on; a spectator's are already marked
+            match &mut packet {
+                Packet::Snapshot(snapshot) if hosting => {
+                    snapshot.opponent = true;
+                    broadcast(&spectators, &json_line(&packet)?);
+                }
+                _ => {}
+            }
+            if incoming.send(LinkEvent::Packet(packet)).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+type Spectators = Arc<Mutex<Vec<Sender<String>>>>;
+type Lines = tokio::io::Lines<BufReader<OwnedReadHalf>>;
+
+fn json_line(packet: &Packet) -> io::Result<String> {
+    let mut line = serde_json::to_string(packet)?;
+    line.push('\n');
+    Ok(line)
+}
+
+// The newcomer's first packet, or None if it hung up or sent nonsense
+async fn read_first(reader: OwnedReadHalf) -> io::Result<(Lines, Option<Packet>)> {
+    let mut lines = BufReader::new(reader).lines();
+    let packet = lines
+        .next_line()
+        .await?
+        .and_then(|line| serde_json::from_str(&line).ok());
+    Ok((lines, packet))
+}
+
+// Once the game has its opponent, anyone else who connects can only watch.
+// Each newcomer is heard out on a task of its own, so one that stays quiet
+// doesn't keep the others waiting and one that fails only gets logged
+async fn accept_spectators(listener: TcpListener, spectators: Spectators) {
+    loop {
+        let (reader, writer) = match listener.accept().await {
+            Ok((stream, _)) => stream.into_split(),
+            Err(err) => {
+                tracing::warn!("failed to accept a spectator: {err}");
+                continue;
+            }
+        };
+        let spectators = spectators.clone();
+        tokio::spawn(async move {
+            match read_first(reader).await {
+                Ok((_, Some(Packet::Watch))) => add_spectator(&spectators, writer),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("failed to hear from a spectator: {err}"),
+            }
+        });
+    }
+}
+
+// Each spectator gets its own writer, so a slow one can't hold up the game
+fn add_spectator(spectators: &Spectators, mut writer: OwnedWriteHalf) {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let handle = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        while let Ok(line) = receiver.recv() {
+            if handle.block_on(writer.write_all(line.as_bytes())).is_err() {
+                break;
+            }
+        }
+    });
+    spectators.lock().unwrap().push(sender);
+}
+
+// Spectators who have gone away are dropped along the way
+fn broadcast(spectators: &Spectators, line: &str) {
+    spectators
+        .lock()
+        .unwrap()
+        .retain(|spectator| spectator.send(line.to_string()).is_ok());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{CellState, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn snapshot(username: &str) -> BoardSnapshot {
+        BoardSnapshot {
+            username: username.to_string(),
+            board: vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT],
+            score: 0,
+            level: 1,
+            lines: 0,
+            game_over: false,
+            stack_height: 0,
+            opponent: false,
+        }
+    }
+
+    fn deadline() -> Instant {
+        Instant::now() + Duration::from_secs(5)
+    }
+
+    fn wait_connected(link: &NetworkLink) {
+        let deadline = deadline();
+        while link.status() != NetworkStatus::Connected {
+            assert!(Instant::now() < deadline, "never connected");
+            link.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn spectators_see_both_boards_through_the_host() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        let address = format!("127.0.0.1:{port}");
+        let host = NetworkLink::start(NetworkMode::Server { port });
+        std::thread::sleep(Duration::from_millis(100));
+        let opponent = NetworkLink::start(NetworkMode::Client {
+            address: address.clone(),
+        });
+        // The host only counts the opponent in once it has said hello
+        wait_connected(&opponent);
+        opponent.send_packet(Packet::Hello {
+            username: "opponent".to_string(),
+        });
+        wait_connected(&host);
+        // One that connects and says nothing doesn't hold up the real spectator
+        let _quiet = std::net::TcpStream::connect(&address).unwrap();
+        let spectator = NetworkLink::start(NetworkMode::Spectate { address });
+        wait_connected(&spectator);
+
+        // The host may not have heard the spectator out yet, so keep sending
+        let mut seen = HashSet::new();
+        let deadline = deadline();
+        while seen.len() < 2 {
+            assert!(Instant::now() < deadline, "only saw {seen:?}");
+            host.send_packet(Packet::Snapshot(snapshot("host")));
+            opponent.send_packet(Packet::Snapshot(snapshot("opponent")));
+            std::thread::sleep(Duration::from_millis(20));
+            for packet in spectator.poll() {
+                if let Packet::Snapshot(board) = packet {
+                    seen.insert((board.username, board.opponent));
+                }
+            }
+        }
+        let expected = [("host".to_string(), false), ("opponent".to_string(), true)];
+        assert_eq!(seen, HashSet::from(expected));
+    }
+}
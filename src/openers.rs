@@ -0,0 +1,186 @@
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::{CellState, TetrominoType, BOARD_HEIGHT, BOARD_WIDTH};
+
+// Openings are looked for once this many pieces are down, and not after
+pub const OPENER_PIECES: RangeInclusive<u32> = 5..=10;
+// How many of an opening's cells the board can lack, and how many cells it can
+// have outside the opening, and still count. Four is one piece left out or
+// put somewhere else
+const TOLERANCE: usize = 4;
+
+// A named setup, as the cells each of its pieces fills. A letter used twice in
+// openings.toml lists both pieces' cells under one entry
+pub struct Opening {
+    pub name: String,
+    pub pieces: Vec<(TetrominoType, Vec<(usize, usize)>)>,
+}
+
+// How openings are written in openings.toml, the way challenges.toml draws its
+// boards but with piece letters for the filled cells
+#[derive(Deserialize)]
+struct OpeningEntry {
+    name: String,
+    board: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OpeningFile {
+    opening: Vec<OpeningEntry>,
+}
+
+fn piece_for(letter: char) -> Option<TetrominoType> {
+    Some(match letter {
+        'I' => TetrominoType::I,
+        'J' => TetrominoType::J,
+        'L' => TetrominoType::L,
+        'O' => TetrominoType::O,
+        'S' => TetrominoType::S,
+        'T' => TetrominoType::T,
+        'Z' => TetrominoType::Z,
+        _ => return None,
+    })
+}
+
+// The piece that fills a setup's cells once it's flipped left to right
+fn mirrored_kind(kind: TetrominoType) -> TetrominoType {
+    match kind {
+        TetrominoType::J => TetrominoType::L,
+        TetrominoType::L => TetrominoType::J,
+        TetrominoType::S => TetrominoType::Z,
+        TetrominoType::Z => TetrominoType::S,
+        kind => kind,
+    }
+}
+
+impl From<OpeningEntry> for Opening {
+    fn from(entry: OpeningEntry) -> Self {
+        let top = BOARD_HEIGHT.saturating_sub(entry.board.len());
+        let mut pieces: Vec<(TetrominoType, Vec<(usize, usize)>)> = Vec::new();
+        for (y, line) in (top..).zip(&entry.board) {
+            for (x, letter) in line.chars().enumerate().take(BOARD_WIDTH) {
+                let Some(kind) = piece_for(letter) else {
+                    continue;
+                };
+                match pieces.iter_mut().find(|(listed, _)| *listed == kind) {
+                    Some((_, cells)) => cells.push((x, y)),
+                    None => pieces.push((kind, vec![(x, y)])),
+                }
+            }
+        }
+        Opening {
+            name: entry.name,
+            pieces,
+        }
+    }
+}
+
+impl Opening {
+    fn mirrored(&self) -> Opening {
+        Opening {
+            name: self.name.clone(),
+            pieces: self
+                .pieces
+                .iter()
+                .map(|(kind, cells)| {
+                    let cells = cells.iter().map(|&(x, y)| (BOARD_WIDTH - 1 - x, y));
+                    (mirrored_kind(*kind), cells.collect())
+                })
+                .collect(),
+        }
+    }
+
+    // (cells of the opening the board doesn't have with the right piece in them,
+    // filled cells of the board outside the opening)
+    fn difference(&self, board: &[Vec<CellState>]) -> (usize, usize) {
+        let mut missing = 0;
+        let mut inside = 0;
+        for (kind, cells) in &self.pieces {
+            for &(x, y) in cells {
+                let cell = board[y][x];
+                inside += usize::from(cell.is_filled());
+                missing += usize::from(cell != CellState::Filled(*kind));
+            }
+        }
+        let filled = board
+            .iter()
+            .flatten()
+            .filter(|cell| cell.is_filled())
+            .count();
+        (missing, filled - inside)
+    }
+}
+
+pub struct OpenerDetector {
+    openings: Vec<Opening>,
+}
+
+impl OpenerDetector {
+    pub fn builtin() -> &'static OpenerDetector {
+        static DETECTOR: OnceLock<OpenerDetector> = OnceLock::new();
+        DETECTOR.get_or_init(|| {
+            // coverage: ignore
+            let file: OpeningFile =
+                toml::from_str(include_str!("../openings.toml")).expect("openings.toml is valid");
+            let written: Vec<_> = file.opening.into_iter().map(Opening::from).collect();
+            let mirrors: Vec<_> = written.iter().map(Opening::mirrored).collect();
+            OpenerDetector {
+                openings: written.into_iter().chain(mirrors).collect(),
+            }
+        })
+    }
+
+    // The opening the board is closest to, if it's within TOLERANCE of any
+    pub fn detect(&self, board: &[Vec<CellState>]) -> Option<&str> {
+        self.openings
+            .iter()
+            .filter_map(|opening| {
+                let (missing, extra) = opening.difference(board);
+                (missing <= TOLERANCE && extra <= TOLERANCE)
+                    .then_some((missing + extra, opening.name.as_str()))
+            })
+            .min_by_key(|&(off, _)| off)
+            .map(|(_, name)| name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(pieces: &[(TetrominoType, Vec<(usize, usize)>)]) -> Vec<Vec<CellState>> {
+        let mut board = vec![vec![CellState::Empty; BOARD_WIDTH]; BOARD_HEIGHT];
+        for (kind, cells) in pieces {
+            for &(x, y) in cells {
+                board[y][x] = CellState::Filled(*kind);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn builtin_openings_parse() {
+        let detector = OpenerDetector::builtin();
+        assert_eq!(detector.openings.len(), 4);
+        for opening in &detector.openings {
+            let cells: usize = opening.pieces.iter().map(|(_, cells)| cells.len()).sum();
+            assert_eq!(cells % 4, 0, "{}", opening.name);
+        }
+    }
+
+    #[test]
+    fn recognises_openings_near_enough() {
+        let detector = OpenerDetector::builtin();
+        let tki = &detector.openings[0];
+        assert_eq!(detector.detect(&build(&tki.pieces)), Some("TKI"));
+        // Still TKI one piece short, or flipped the other way
+        assert_eq!(detector.detect(&build(&tki.pieces[1..])), Some("TKI"));
+        assert_eq!(detector.detect(&build(&tki.mirrored().pieces)), Some("TKI"));
+        // Two pieces short is too far off
+        assert_eq!(detector.detect(&build(&tki.pieces[2..])), None);
+        assert_eq!(detector.detect(&build(&[])), None);
+    }
+}
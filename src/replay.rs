@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::Context;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::challenge::ChallengeConfig;
+use crate::config::Config;
+use crate::locale::locale;
+use crate::rotation::RotationDir;
+use crate::spectator::draw_snapshot;
+use crate::strict;
+use crate::theme::Theme;
+use crate::{CellState, InputEvent, TetrisGame};
+
+// One input as the game applied it, along with the keys held for the next spawn
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    // Since the game started
+    pub at_ms: u64,
+    pub event: InputEvent,
+    pub queued_rotation: Option<RotationDir>,
+    pub queued_hold: bool,
+}
+
+// How a game started and every input after, which is enough to play it again.
+// Garbage from an opponent is random, so online games don't replay
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub config: Config,
+    pub seed: u64,
+    pub challenge: Option<ChallengeConfig>,
+    pub board: Vec<Vec<CellState>>,
+    pub events: Vec<ReplayEvent>,
+    // What the recording ended on, to check playback against
+    pub score: u32,
+    pub lines: u32,
+}
+
+impl Replay {
+    pub fn new(config: Config, seed: u64) -> Self {
+        Replay {
+            config,
+            seed,
+            challenge: None,
+            board: Vec::new(),
+            events: Vec::new(),
+            score: 0,
+            lines: 0,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("failed to create the data directory")?;
+        }
+        let text = serde_json::to_string(self)?;
+        fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    // The game as it was before the first input
+    pub fn start(&self) -> TetrisGame {
+        let mut game = TetrisGame::seeded(self.config.clone(), self.seed);
+        if let Some(challenge) = &self.challenge {
+            game = game.with_challenge(challenge.clone());
+        }
+        game.board = self.board.clone();
+        game
+    }
+
+    // Plays through like play_through, but stops at the first input that breaks
+    // the guideline rules. Playback runs faster than the game did, so the lock
+    // delay never runs out and moving after it can't be caught
+    pub fn validate(&self) -> anyhow::Result<TetrisGame> {
+        let mut game = self.start();
+        for (index, event) in self.events.iter().enumerate() {
+            strict::validate_action(&game, event.event).with_context(|| {
+                format!("input {index} ({:?} at {} ms)", event.event, event.at_ms)
+            })?;
+            apply(&mut game, event);
+        }
+        Ok(game)
+    }
+
+    // The game after every recorded input
+    pub fn play_through(&self) -> TetrisGame {
+        let mut game = self.start();
+        for event in &self.events {
+            apply(&mut game, event);
+        }
+        game
+    }
+}
+
+fn apply(game: &mut TetrisGame, event: &ReplayEvent) {
+    game.queued_rotation = event.queued_rotation;
+    game.queued_hold = event.queued_hold;
+    game.apply_input(event.event);
+}
+
+// Plays a replay back in real time, the inputs landing when they did originally
+pub struct ReplayViewer {
+    replay: Replay,
+    game: TetrisGame,
+    next: usize,
+    started_at: Instant,
+    theme: Theme,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayViewer {
+            game: replay.start(),
+            theme: replay.config.theme.theme(),
+            replay,
+            next: 0,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl eframe::App for ReplayViewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let elapsed = self.started_at.elapsed().as_millis() as u64;
+        while let Some(event) = self.replay.events.get(self.next) {
+            if event.at_ms > elapsed {
+                break;
+            }
+            apply(&mut self.game, event);
+            self.next += 1;
+        }
+        let language = self.replay.config.language;
+        let t = locale(language);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(t.get("replay"));
+                if ui.button(t.get("restart_replay")).clicked() {
+                    *self = ReplayViewer::new(self.replay.clone());
+                }
+            });
+            ui.label(t.fill("replay_progress", &[&self.next, &self.replay.events.len()]));
+            draw_snapshot(ui, &self.game.snapshot(), &self.theme, language);
+        });
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessDriver;
+
+    #[test]
+    fn replays_end_where_the_game_did() {
+        let mut driver = HeadlessDriver::new(Config::default());
+        driver.run(60);
+        let game = &driver.game;
+        let replay = game.replay.lock().unwrap().clone();
+        assert!(!replay.events.is_empty());
+
+        // Through a file and back, like a saved replay
+        let text = serde_json::to_string(&replay).unwrap();
+        let replay: Replay = serde_json::from_str(&text).unwrap();
+        let replayed = replay.play_through();
+        assert_eq!(replayed.board, game.board);
+        assert_eq!(replayed.score, game.score);
+        assert_eq!(replayed.lines_cleared, game.lines_cleared);
+    }
+
+    #[test]
+    fn validation_finds_the_input_that_broke_the_rules() {
+        let mut replay = Replay::new(Config::default(), 1);
+        replay.board = TetrisGame::seeded(Config::default(), 1).board;
+        let event = |event| ReplayEvent {
+            at_ms: 0,
+            event,
+            queued_rotation: None,
+            queued_hold: false,
+        };
+        replay.events = vec![event(InputEvent::Hold), event(InputEvent::MoveLeft)];
+        assert!(replay.validate().is_ok());
+        replay.events.push(event(InputEvent::Hold));
+        let err = replay.validate().err().unwrap();
+        assert_eq!(
+            format!("{err:#}"),
+            "input 2 (Hold at 0 ms): held twice before a piece locked"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::TetrominoType;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationDir {
+    Clockwise,
+    Counterclockwise,
+}
+
+impl RotationDir {
+    pub fn reversed(self) -> Self {
+        match self {
+            RotationDir::Clockwise => RotationDir::Counterclockwise,
+            RotationDir::Counterclockwise => RotationDir::Clockwise,
+        }
+    }
+}
+
+// How the rotate keys turn the falling piece
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationSystem {
+    // Clockwise through all four states
+    Super,
+    // The NES: clockwise, no kicks, I, S and Z only have two states
+    Classic,
+    // Counterclockwise like the Sega arcade game, otherwise the same as Classic
+    Sega,
+    // Sega rotation that tries one cell right, then one left, when blocked
+    Arika,
+}
+
+impl RotationSystem {
+    pub const ALL: [RotationSystem; 4] = [
+        RotationSystem::Super,
+        RotationSystem::Classic,
+        RotationSystem::Sega,
+        RotationSystem::Arika,
+    ];
+
+    // Flips between the spawn shape and a single quarter turn. On the NES that's
+    // the I, S and Z; J, L and T go through all four states there, since their
+    // turned shapes all look different and the game needs every one of them
+    pub fn two_state(self, kind: TetrominoType) -> bool {
+        self != RotationSystem::Super
+            && matches!(kind, TetrominoType::I | TetrominoType::S | TetrominoType::Z)
+    }
+
+    // The direction of the main rotate key; the other key turns the opposite way
+    pub fn primary(self) -> RotationDir {
+        match self {
+            RotationSystem::Super | RotationSystem::Classic => RotationDir::Clockwise,
+            RotationSystem::Sega | RotationSystem::Arika => RotationDir::Counterclockwise,
+        }
+    }
+
+    // Sideways offsets tried in order until the turned piece fits
+    pub fn kicks(self) -> &'static [i32] {
+        match self {
+            RotationSystem::Arika => &[0, 1, -1],
+            _ => &[0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_i_s_and_z_have_two_states() {
+        for kind in TetrominoType::ALL {
+            let nes = matches!(kind, TetrominoType::I | TetrominoType::S | TetrominoType::Z);
+            assert_eq!(RotationSystem::Classic.two_state(kind), nes, "{kind:?}");
+            assert!(!RotationSystem::Super.two_state(kind));
+        }
+    }
+}
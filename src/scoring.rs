@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+// Which ruleset awards points for cleared lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoringSystem {
+    Guideline,
+    Nes,
+    GameBoy,
+    Classic,
+}
+
+impl ScoringSystem {
+    pub const ALL: [ScoringSystem; 4] = [
+        ScoringSystem::Guideline,
+        ScoringSystem::Nes,
+        ScoringSystem::GameBoy,
+        ScoringSystem::Classic,
+    ];
+
+    // Only the guideline tells T-spins apart from the same lines cleared plainly
+    pub fn compute(self, lines: u32, t_spin: bool, level: u32) -> u32 {
+        match self {
+            ScoringSystem::Guideline => guideline(lines, t_spin, level),
+            ScoringSystem::Nes => nes(lines, level),
+            ScoringSystem::GameBoy => game_boy(lines, level),
+            ScoringSystem::Classic => classic(lines, level),
+        }
+    }
+
+    // Guideline pays half again for a tetris or T-spin right after another one
+    pub fn back_to_back_bonus(self, points: u32) -> u32 {
+        match self {
+            ScoringSystem::Guideline => points / 2,
+            _ => 0,
+        }
+    }
+}
+
+// A T-spin pays even when it clears nothing
+fn guideline(lines: u32, t_spin: bool, level: u32) -> u32 {
+    let base = match lines {
+        _ if t_spin => 400 * (lines.min(3) + 1),
+        0 => 0,
+        1 => 100,
+        2 => 300,
+        3 => 500,
+        _ => 800,
+    };
+    base * level
+}
+
+// The consoles count levels from 0, so their `level + 1` is our level
+fn nes(lines: u32, level: u32) -> u32 {
+    let base = match lines {
+        0 => 0,
+        1 => 40,
+        2 => 100,
+        3 => 300,
+        _ => 1200,
+    };
+    base * level
+}
+
+// The Game Boy pays the same for lines as the NES; it differs in soft drop
+// points, which nothing here awards
+fn game_boy(lines: u32, level: u32) -> u32 {
+    nes(lines, level)
+}
+
+fn classic(lines: u32, _level: u32) -> u32 {
+    lines * 100
+}
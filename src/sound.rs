@@ -0,0 +1,80 @@
+use std::io::Cursor;
+use std::sync::mpsc::{self, Sender};
+
+use anyhow::Context;
+use rodio::source::{Buffered, Source, Speed};
+use rodio::{Decoder, OutputStream};
+
+use crate::TetrominoType;
+
+type LockSound = Buffered<Speed<Decoder<Cursor<&'static [u8]>>>>;
+
+// How fast each piece plays the one lock recording, by TetrominoType as usize.
+// Slower is lower: the I lands with a thud, the O with a click and the rest
+// clack somewhere in between
+const LOCK_SPEEDS: [f32; 7] = [0.6, 0.9, 0.95, 1.6, 1.1, 1.0, 1.15];
+
+fn lock_sounds() -> anyhow::Result<Vec<LockSound>> {
+    LOCK_SPEEDS
+        .iter()
+        .map(|&speed| {
+            let wav = Cursor::new(&include_bytes!("../sounds/lock.wav")[..]);
+            Ok(Decoder::new_wav(wav)?.speed(speed).buffered())
+        })
+        .collect::<anyhow::Result<_>>()
+        .context("failed to decode sounds/lock.wav")
+}
+
+// The game's end of the audio output. The stream can't leave the thread that
+// opened it, so it lives on one of its own, playing what the game sends over
+pub struct SoundEngine {
+    plays: Sender<TetrominoType>,
+}
+
+impl SoundEngine {
+    pub fn start() -> anyhow::Result<Self> {
+        let locks = lock_sounds()?;
+        let (plays, requests) = mpsc::channel::<TetrominoType>();
+        let (opened_tx, opened) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (_stream, output) = match OutputStream::try_default() {
+                Ok(stream) => stream,
+                Err(err) => {
+                    let _ = opened_tx.send(Err(err));
+                    return;
+                }
+            };
+            let _ = opened_tx.send(Ok(()));
+            while let Ok(kind) = requests.recv() {
+                let sound = locks[kind as usize].clone();
+                if let Err(err) = output.play_raw(sound.convert_samples()) {
+                    tracing::warn!("failed to play the lock sound: {err}");
+                }
+            }
+        });
+        opened
+            .recv()
+            .context("the audio thread stopped")?
+            .context("failed to open audio")?;
+        Ok(SoundEngine { plays })
+    }
+
+    pub fn play_lock(&self, kind: TetrominoType) {
+        let _ = self.plays.send(kind);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_piece_gets_its_own_pitch_of_the_same_sound() {
+        let locks = lock_sounds().unwrap();
+        assert_eq!(locks.len(), 7);
+        // Speed plays the same samples at another rate, which is the pitch
+        let rate = |kind: TetrominoType| locks[kind as usize].sample_rate();
+        assert!(rate(TetrominoType::I) < rate(TetrominoType::T));
+        assert!(rate(TetrominoType::T) < rate(TetrominoType::O));
+    }
+}
@@ -0,0 +1,146 @@
+use eframe::egui;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::{locale, Language};
+use crate::net::{NetworkLink, NetworkMode, NetworkStatus, Packet};
+use crate::theme::Theme;
+use crate::{CellState, BOARD_HEIGHT, BOARD_WIDTH};
+
+const SPECTATOR_BLOCK_SIZE: f32 = 16.0;
+// Two games of two players each
+const MAX_BOARDS: usize = 4;
+
+// A player's board as spectators see it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoardSnapshot {
+    pub username: String,
+    // The locked cells with the falling piece drawn in
+    pub board: Vec<Vec<CellState>>,
+    pub score: u32,
+    pub level: u32,
+    pub lines: u32,
+    pub game_over: bool,
+    // Locked rows only, for king of the hill
+    #[serde(default)]
+    pub stack_height: usize,
+    // Set by the host on its opponent's boards as it passes them on, so the
+    // two can be told apart even under the same name
+    #[serde(default)]
+    pub opponent: bool,
+}
+
+// Watches hosted games without playing, one tile per player
+pub struct SpectatorClient {
+    links: Vec<NetworkLink>,
+    // The latest board from each player, by which link it came over
+    boards: Vec<(usize, BoardSnapshot)>,
+    theme: Theme,
+    language: Language,
+}
+
+impl SpectatorClient {
+    pub fn new(addresses: Vec<String>, theme: Theme, language: Language) -> Self {
+        SpectatorClient {
+            links: addresses
+                .into_iter()
+                .map(|address| NetworkLink::start(NetworkMode::Spectate { address }))
+                .collect(),
+            boards: Vec::new(),
+            theme,
+            language,
+        }
+    }
+
+    fn poll(&mut self) {
+        for (link, network) in self.links.iter().enumerate() {
+            for packet in network.poll() {
+                let Packet::Snapshot(snapshot) = packet else {
+                    continue;
+                };
+                let known = self
+                    .boards
+                    .iter()
+                    .position(|(from, board)| *from == link && board.opponent == snapshot.opponent);
+                match known {
+                    Some(index) => self.boards[index].1 = snapshot,
+                    None if self.boards.len() < MAX_BOARDS => self.boards.push((link, snapshot)),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+impl eframe::App for SpectatorClient {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll();
+        let t = locale(self.language);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(t.get("spectating"));
+            for network in &self.links {
+                if let NetworkStatus::Disconnected(reason) = network.status() {
+                    ui.colored_label(
+                        Color32::RED,
+                        format!("{}: {reason}", t.get("connection_lost")),
+                    );
+                }
+            }
+            if self.boards.is_empty() {
+                ui.label(t.get("waiting_for_players"));
+            }
+            // Two boards to a row
+            for row in self.boards.chunks(2) {
+                ui.horizontal(|ui| {
+                    for (_, snapshot) in row {
+                        draw_snapshot(ui, snapshot, &self.theme, self.language);
+                        ui.add_space(SPECTATOR_BLOCK_SIZE);
+                    }
+                });
+            }
+        });
+        ctx.request_repaint();
+    }
+}
+
+// A board drawn read-only, with its player's name and numbers above it
+pub fn draw_snapshot(
+    ui: &mut egui::Ui,
+    snapshot: &BoardSnapshot,
+    theme: &Theme,
+    language: Language,
+) {
+    let t = locale(language);
+    ui.vertical(|ui| {
+        ui.strong(&snapshot.username);
+        ui.label(t.fill("score", &[&snapshot.score]));
+        ui.label(t.fill("level", &[&snapshot.level]));
+        ui.label(t.fill("lines_count", &[&snapshot.lines]));
+        let size = egui::vec2(BOARD_WIDTH as f32, BOARD_HEIGHT as f32) * SPECTATOR_BLOCK_SIZE;
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, theme.background);
+        for (y, row) in snapshot.board.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if let Some(color) = theme.cell_color(cell) {
+                    let cell_rect = egui::Rect::from_min_size(
+                        rect.min + egui::vec2(x as f32, y as f32) * SPECTATOR_BLOCK_SIZE,
+                        egui::Vec2::splat(SPECTATOR_BLOCK_SIZE),
+                    );
+                    painter.rect_filled(cell_rect.shrink(0.5), 0.0, color);
+                }
+            }
+        }
+        painter.rect_stroke(rect, 0.0, theme.grid_stroke());
+        if snapshot.game_over {
+            painter.rect_filled(rect, 0.0, Color32::from_black_alpha(120));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                t.get("game_over"),
+                egui::FontId::proportional(20.0),
+                Color32::WHITE,
+            );
+        }
+    });
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::time::Instant;
+
+use crate::{InputEvent, TetrisGame, LOCK_DELAY, MAX_SPINS_IN_PLACE};
+
+// A player action the guideline wouldn't allow, which Config::strict_mode refuses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleViolation {
+    // The piece had rested for the whole lock delay and should have locked
+    MoveAfterLock,
+    // Hold was already used on this piece
+    HoldUsed,
+    // The piece spun in place more than MAX_SPINS_IN_PLACE times
+    RotationPastCap,
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RuleViolation::MoveAfterLock => "moved after the lock delay ran out",
+            RuleViolation::HoldUsed => "held twice before a piece locked",
+            RuleViolation::RotationPastCap => "rotated in place past the cap",
+        })
+    }
+}
+
+impl std::error::Error for RuleViolation {}
+
+// Gravity and locking come from the game itself, so only the player's actions
+// are checked
+pub fn validate_action(game: &TetrisGame, event: InputEvent) -> Result<(), RuleViolation> {
+    let moves = matches!(
+        event,
+        InputEvent::MoveLeft
+            | InputEvent::MoveRight
+            | InputEvent::SoftDrop
+            | InputEvent::Rotate
+            | InputEvent::RotateReverse
+    );
+    let locked = game
+        .lock_timer
+        .is_some_and(|resting_since| Instant::now() - resting_since >= LOCK_DELAY);
+    if moves && locked {
+        return Err(RuleViolation::MoveAfterLock);
+    }
+    if event == InputEvent::Hold && !game.can_hold {
+        return Err(RuleViolation::HoldUsed);
+    }
+    let rotation = matches!(event, InputEvent::Rotate | InputEvent::RotateReverse);
+    if rotation
+        && game.lock_timer.is_some()
+        && game.current_piece.y <= game.lowest_y
+        && game.spins_in_place > MAX_SPINS_IN_PLACE
+    {
+        return Err(RuleViolation::RotationPastCap);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn catches_each_rule() {
+        let mut game = TetrisGame::new(Config::default());
+        assert_eq!(validate_action(&game, InputEvent::Hold), Ok(()));
+        game.can_hold = false;
+        assert_eq!(
+            validate_action(&game, InputEvent::Hold),
+            Err(RuleViolation::HoldUsed)
+        );
+
+        game.lock_timer = Some(Instant::now());
+        game.lowest_y = game.current_piece.y;
+        game.spins_in_place = MAX_SPINS_IN_PLACE + 1;
+        assert_eq!(
+            validate_action(&game, InputEvent::Rotate),
+            Err(RuleViolation::RotationPastCap)
+        );
+        assert_eq!(validate_action(&game, InputEvent::MoveLeft), Ok(()));
+
+        game.lock_timer = Some(Instant::now() - LOCK_DELAY);
+        assert_eq!(
+            validate_action(&game, InputEvent::MoveLeft),
+            Err(RuleViolation::MoveAfterLock)
+        );
+        assert_eq!(validate_action(&game, InputEvent::Lock), Ok(()));
+    }
+}
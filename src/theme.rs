@@ -0,0 +1,279 @@
+use eframe::egui;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::{CellState, TetrominoType, BOARD_HEIGHT, BOARD_WIDTH};
+
+// Color32 has no serde support, so anything that gets written to disk uses this
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerColor(pub u8, pub u8, pub u8, pub u8);
+
+impl From<Color32> for SerColor {
+    fn from(color: Color32) -> Self {
+        let [r, g, b, a] = color.to_array();
+        SerColor(r, g, b, a)
+    }
+}
+
+impl From<SerColor> for Color32 {
+    fn from(color: SerColor) -> Self {
+        Color32::from_rgba_premultiplied(color.0, color.1, color.2, color.3)
+    }
+}
+
+// Colors the board is drawn with
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub background: SerColor,
+    pub grid: SerColor,
+    // Width of the board's grid lines; 0 leaves them out
+    #[serde(default = "standard_grid_thickness")]
+    pub grid_thickness: f32,
+    // Indexed in TetrominoType::ALL order
+    pub pieces: [SerColor; 7],
+    pub garbage: SerColor,
+    #[serde(default)]
+    pub pattern: BackgroundPattern,
+    #[serde(default)]
+    pub piece_shadow: bool,
+    #[serde(default)]
+    pub style: RenderStyle,
+}
+
+// A faint texture painted under the board cells
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackgroundPattern {
+    #[default]
+    None,
+    Grid,
+    Dots,
+    Checkerboard,
+    Stripes,
+}
+
+impl BackgroundPattern {
+    pub const ALL: [BackgroundPattern; 5] = [
+        BackgroundPattern::None,
+        BackgroundPattern::Grid,
+        BackgroundPattern::Dots,
+        BackgroundPattern::Checkerboard,
+        BackgroundPattern::Stripes,
+    ];
+}
+
+const PATTERN_ALPHA: f32 = 0.08;
+
+// How the cells themselves are painted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderStyle {
+    #[default]
+    Flat,
+    // Each cell glows in its own color, faked with larger translucent copies behind it
+    Neon,
+}
+
+impl RenderStyle {
+    pub const ALL: [RenderStyle; 2] = [RenderStyle::Flat, RenderStyle::Neon];
+}
+
+// The neon glow passes, outermost first, as (how far past the cell they reach
+// as a share of its size, alpha)
+const NEON_GLOW_PASSES: [(f32, f32); 2] = [(0.5, 0.08), (0.2, 0.3)];
+
+// How the board's grid lines look, on top of whatever color the theme gives them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridStyle {
+    Subtle,
+    #[default]
+    Standard,
+    // Lines in whichever of black or white stands out against the background
+    Bold,
+    None,
+}
+
+impl GridStyle {
+    pub const ALL: [GridStyle; 4] = [
+        GridStyle::Subtle,
+        GridStyle::Standard,
+        GridStyle::Bold,
+        GridStyle::None,
+    ];
+}
+
+fn standard_grid_thickness() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+}
+
+impl ThemePreset {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemePreset::Dark => Theme::dark(),
+            ThemePreset::Light => Theme::light(),
+        }
+    }
+}
+
+// Guideline colors for I, J, L, O, S, T and Z
+const GUIDELINE_PIECES: [SerColor; 7] = [
+    SerColor(0, 240, 240, 255),
+    SerColor(0, 0, 240, 255),
+    SerColor(240, 160, 0, 255),
+    SerColor(240, 240, 0, 255),
+    SerColor(0, 240, 0, 255),
+    SerColor(160, 0, 240, 255),
+    SerColor(240, 0, 0, 255),
+];
+
+// Locale keys naming the guideline colors, in the same order
+const GUIDELINE_COLOR_NAMES: [&str; 7] = [
+    "color_cyan",
+    "color_blue",
+    "color_orange",
+    "color_yellow",
+    "color_green",
+    "color_purple",
+    "color_red",
+];
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            background: Color32::from_gray(20).into(),
+            grid: Color32::GRAY.into(),
+            grid_thickness: standard_grid_thickness(),
+            pieces: GUIDELINE_PIECES,
+            garbage: Color32::from_gray(100).into(),
+            pattern: BackgroundPattern::None,
+            piece_shadow: false,
+            style: RenderStyle::Flat,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            background: Color32::from_gray(235).into(),
+            grid: Color32::from_gray(170).into(),
+            grid_thickness: standard_grid_thickness(),
+            pieces: GUIDELINE_PIECES,
+            garbage: Color32::from_gray(130).into(),
+            pattern: BackgroundPattern::None,
+            piece_shadow: false,
+            style: RenderStyle::Flat,
+        }
+    }
+
+    pub fn piece_color(&self, kind: TetrominoType) -> Color32 {
+        self.pieces[kind as usize].into()
+    }
+
+    // The locale key for what color the piece spawns in. Every preset uses the
+    // guideline colors, so the names hold for any of them
+    pub fn piece_color_name(&self, kind: TetrominoType) -> &'static str {
+        GUIDELINE_COLOR_NAMES[kind as usize]
+    }
+
+    pub fn cell_color(&self, cell: CellState) -> Option<Color32> {
+        match cell {
+            CellState::Empty => None,
+            CellState::Filled(kind) => Some(self.piece_color(kind)),
+            CellState::Garbage => Some(self.garbage.into()),
+        }
+    }
+
+    pub fn set_grid_style(&mut self, style: GridStyle) {
+        self.grid_thickness = match style {
+            GridStyle::Subtle => 0.5,
+            GridStyle::Standard => standard_grid_thickness(),
+            GridStyle::Bold => 1.5,
+            GridStyle::None => 0.0,
+        };
+        if style == GridStyle::Bold {
+            let dark = egui::Rgba::from(Color32::from(self.background)).intensity() < 0.5;
+            self.grid = if dark { Color32::WHITE } else { Color32::BLACK }.into();
+        }
+    }
+
+    pub fn grid_stroke(&self) -> egui::Stroke {
+        egui::Stroke::new(self.grid_thickness, self.grid)
+    }
+
+    // Drawn in the grid color so it reads on both light and dark backgrounds
+    pub fn paint_pattern(&self, painter: &egui::Painter, rect: egui::Rect, block_size: f32) {
+        let color = Color32::from(self.grid).gamma_multiply(PATTERN_ALPHA);
+        let cell = |x: usize, y: usize| {
+            egui::Rect::from_min_size(
+                rect.min + egui::vec2(x as f32, y as f32) * block_size,
+                egui::Vec2::splat(block_size),
+            )
+        };
+        match self.pattern {
+            BackgroundPattern::None => {}
+            // A finer grid between the cell lines
+            BackgroundPattern::Grid => {
+                let stroke = egui::Stroke::new(1.0, color);
+                for x in 0..BOARD_WIDTH {
+                    let x = rect.left() + (x as f32 + 0.5) * block_size;
+                    painter.vline(x, rect.y_range(), stroke);
+                }
+                for y in 0..BOARD_HEIGHT {
+                    let y = rect.top() + (y as f32 + 0.5) * block_size;
+                    painter.hline(rect.x_range(), y, stroke);
+                }
+            }
+            BackgroundPattern::Dots => {
+                for y in 0..BOARD_HEIGHT {
+                    for x in 0..BOARD_WIDTH {
+                        painter.circle_filled(cell(x, y).center(), block_size * 0.12, color);
+                    }
+                }
+            }
+            BackgroundPattern::Checkerboard => {
+                for y in 0..BOARD_HEIGHT {
+                    for x in (y % 2..BOARD_WIDTH).step_by(2) {
+                        painter.rect_filled(cell(x, y), 0.0, color);
+                    }
+                }
+            }
+            BackgroundPattern::Stripes => {
+                for y in (0..BOARD_HEIGHT).step_by(2) {
+                    painter.rect_filled(cell(0, y).union(cell(BOARD_WIDTH - 1, y)), 0.0, color);
+                }
+            }
+        }
+    }
+
+    // The glow behind a cell in the neon style, nothing otherwise. Every cell's
+    // glow goes down before any of the cells, or it would wash over its neighbors
+    pub fn paint_glow(&self, painter: &egui::Painter, corners: [egui::Pos2; 4], color: Color32) {
+        if self.style != RenderStyle::Neon {
+            return;
+        }
+        let center = corners
+            .iter()
+            .fold(egui::Pos2::ZERO, |sum, p| sum + p.to_vec2() / 4.0);
+        for (reach, alpha) in NEON_GLOW_PASSES {
+            painter.add(egui::Shape::convex_polygon(
+                corners
+                    .map(|p| center + (p - center) * (1.0 + reach))
+                    .to_vec(),
+                color.gamma_multiply(alpha),
+                egui::Stroke::NONE,
+            ));
+        }
+    }
+
+    // Follows egui's visuals, which track the system theme
+    pub fn system_adaptive(ctx: &egui::Context) -> Self {
+        if ctx.style().visuals.dark_mode {
+            Theme::dark()
+        } else {
+            Theme::light()
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::{InputEvent, TetrominoType};
+
+const TIP_DURATION: Duration = Duration::from_secs(3);
+// Marathon keeps showing tips to anyone with fewer finished games than this
+pub const BEGINNER_GAMES: u32 = 3;
+pub const TUTORIAL_BONUS: u32 = 500;
+
+// The first-game walkthrough, one action at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TutorialStep {
+    Move,
+    SoftDrop,
+    HardDrop,
+    Rotate,
+    Hold,
+    ClearLine,
+}
+
+impl TutorialStep {
+    pub fn next(self) -> Option<TutorialStep> {
+        match self {
+            TutorialStep::Move => Some(TutorialStep::SoftDrop),
+            TutorialStep::SoftDrop => Some(TutorialStep::HardDrop),
+            TutorialStep::HardDrop => Some(TutorialStep::Rotate),
+            TutorialStep::Rotate => Some(TutorialStep::Hold),
+            TutorialStep::Hold => Some(TutorialStep::ClearLine),
+            TutorialStep::ClearLine => None,
+        }
+    }
+
+    // Whether the player did what this step asks, given the lines `event` cleared
+    pub fn done_by(self, event: InputEvent, lines_cleared: u32) -> bool {
+        match self {
+            TutorialStep::Move => {
+                matches!(event, InputEvent::MoveLeft | InputEvent::MoveRight)
+            }
+            TutorialStep::SoftDrop => event == InputEvent::SoftDrop,
+            TutorialStep::HardDrop => event == InputEvent::HardDrop,
+            TutorialStep::Rotate => {
+                matches!(event, InputEvent::Rotate | InputEvent::RotateReverse)
+            }
+            TutorialStep::Hold => event == InputEvent::Hold,
+            TutorialStep::ClearLine => lines_cleared > 0,
+        }
+    }
+
+    pub fn instruction_key(self) -> &'static str {
+        match self {
+            TutorialStep::Move => "tutorial_move",
+            TutorialStep::SoftDrop => "tutorial_soft_drop",
+            TutorialStep::HardDrop => "tutorial_hard_drop",
+            TutorialStep::Rotate => "tutorial_rotate",
+            TutorialStep::Hold => "tutorial_hold",
+            TutorialStep::ClearLine => "tutorial_clear_line",
+        }
+    }
+
+    // The key to point at, if the step is about one
+    pub fn key_label(self) -> Option<&'static str> {
+        match self {
+            TutorialStep::Move => Some("← →"),
+            TutorialStep::SoftDrop => Some("↓"),
+            TutorialStep::HardDrop => Some("Space"),
+            TutorialStep::Rotate => Some("↑"),
+            TutorialStep::Hold => Some("C"),
+            TutorialStep::ClearLine => None,
+        }
+    }
+}
+
+// Introduces each piece the first time it turns up
+#[derive(Clone, Default)]
+pub struct TutorialSystem {
+    tip: Option<(TetrominoType, Instant)>,
+}
+
+impl TutorialSystem {
+    // `shown` is the player's tips so far, so each piece is only introduced once
+    pub fn on_spawn(&mut self, kind: TetrominoType, shown: &mut HashSet<TetrominoType>) {
+        if shown.insert(kind) {
+            self.tip = Some((kind, Instant::now()));
+        }
+    }
+
+    pub fn dismiss(&mut self) {
+        self.tip = None;
+    }
+
+    // The piece whose tip is up, until it times out
+    pub fn current(&self) -> Option<TetrominoType> {
+        self.tip
+            .filter(|(_, shown)| shown.elapsed() < TIP_DURATION)
+            .map(|(kind, _)| kind)
+    }
+}
+
+pub fn tip_key(kind: TetrominoType) -> &'static str {
+    match kind {
+        TetrominoType::I => "tip_i",
+        TetrominoType::J => "tip_j",
+        TetrominoType::L => "tip_l",
+        TetrominoType::O => "tip_o",
+        TetrominoType::S => "tip_s",
+        TetrominoType::T => "tip_t",
+        TetrominoType::Z => "tip_z",
+    }
+}
@@ -0,0 +1,2194 @@
+use std::sync::atomic::Ordering;
+#[cfg(feature = "sound")]
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Context;
+use eframe::egui;
+use egui::Color32;
+
+use crate::ai::{AiBot, Difficulty};
+use crate::bag::{Randomizer, PREVIEW_MAX};
+use crate::cli::PlayArgs;
+use crate::config::Config;
+use crate::four_player::FourPlayerGame;
+use crate::game::{
+    CellState, Crown, GameMode, InputEvent, TetrisGame, TetrominoType, BENCHMARK_GAMES,
+    BOARD_HEIGHT, BOARD_WIDTH, GLOW_DURATION, KING_OF_THE_HILL_POWER, LINES_PER_LEVEL, LPM_WINDOW,
+    MAX_STARTING_GARBAGE, PLACEMENT_FEEDBACK_DURATION, REWIND_TOKENS, SCORE_POPUP_DURATION,
+    SHAKE_MAX_AMPLITUDE, TRAIL_DURATION,
+};
+use crate::gravity::{GravityCurve, SpeedMode};
+use crate::handling::{HandlingPreset, HeldKey};
+use crate::high_scores::SortBy;
+use crate::keys;
+use crate::lobby::{Lobby, LobbyOutcome};
+use crate::locale::{locale, Language, Locale};
+use crate::net::NetworkStatus;
+use crate::rotation::RotationSystem;
+use crate::scoring::ScoringSystem;
+#[cfg(feature = "sound")]
+use crate::sound::SoundEngine;
+use crate::spectator::SpectatorClient;
+use crate::theme::{BackgroundPattern, GridStyle, RenderStyle, Theme, ThemePreset};
+use crate::{ai, challenge, daily, tutorial};
+
+const BLOCK_SIZE: f32 = 23.0;
+const LEVEL_UP_FLASH_DURATION: Duration = Duration::from_millis(600);
+const OPENER_FLASH_DURATION: Duration = Duration::from_millis(1500);
+const LOCK_FLASH_BRIGHTNESS: f32 = 1.5;
+const CROWN_COLOR: Color32 = Color32::GOLD;
+const LEVEL_METER_BARS: u32 = 15;
+const LEVEL_METER_WIDTH: f32 = 10.0;
+const AXIS_LABEL_SIZE: f32 = 10.0;
+// Between the row numbers and the board's left edge
+const AXIS_LABEL_GAP: f32 = 1.0;
+const GAME_OVER_CLEAR_STEP: Duration = Duration::from_millis(50);
+const MANUAL_HINT_PIECES: u32 = 10;
+const PREVIEW_COLUMN_LENGTH: usize = 3;
+const PIECE_SHADOW_OFFSET: egui::Vec2 = egui::vec2(3.0, 3.0);
+const CELL_LABEL_MIN_BLOCK: f32 = 20.0;
+// Fills the cells of a piece waiting for Enter in training wheels
+const PLACEMENT_HIGHLIGHT: Color32 = Color32::from_rgba_premultiplied(80, 80, 0, 80);
+// The debug overlay's marks for the falling piece's center of mass and rotation center
+const PIECE_MARKER_RADIUS: f32 = 3.0;
+const HARD_DROP_CONFIRM_WINDOW: Duration = Duration::from_secs(1);
+const PENDING_GARBAGE_BAR_WIDTH: f32 = 4.0;
+// How dark the overlay on a background image is, so pieces stand out from it
+const BACKGROUND_IMAGE_DIMMING: u8 = 120;
+const METRONOME_RADIUS: f32 = 4.0;
+const FIRST_GAME_CONTROLS_DURATION: Duration = Duration::from_secs(5);
+const REWIND_TOKEN_RADIUS: f32 = 4.0;
+
+impl TetrisGame {
+    // Left, right and soft drop, which repeat while held. A piece still sliding
+    // in doesn't move yet, but letting go meanwhile ends the hold all the same,
+    // so the piece doesn't catch up on repeats for a key that's back up
+    fn hold_movement_keys(&mut self, input: &egui::InputState, controllable: bool) {
+        let now = Instant::now();
+        let handling = self.config.handling;
+        let bindings = self.config.key_bindings.clone();
+
+        // Left and right move on the press, then repeat with the handling's DAS
+        // and ARR; the last one pressed wins. Training wheels doesn't repeat at
+        // all
+        for (keys, event) in [
+            (&bindings.move_left, InputEvent::MoveLeft),
+            (&bindings.move_right, InputEvent::MoveRight),
+        ] {
+            if keys::first_press(input, keys) {
+                self.shift = Some((event, HeldKey::new(now)));
+                self.finesse_presses += 1;
+            }
+        }
+        if let Some((event, mut held)) = self.shift {
+            let (keys, dx) = match event {
+                InputEvent::MoveLeft => (&bindings.move_left, -1),
+                _ => (&bindings.move_right, 1),
+            };
+            if controllable {
+                let timing = (handling.das(), handling.arr());
+                self.repeat_held(event, (dx, 0), &mut held, timing);
+            }
+            self.shift = keys::down(input, keys).then_some((event, held));
+        }
+
+        // Soft drop goes a row on the press and repeats SDF times faster than
+        // gravity, after waiting DFR
+        if keys::first_press(input, &bindings.soft_drop) {
+            self.soft_drop = Some(HeldKey::new(now));
+        }
+        if let Some(mut held) = self.soft_drop {
+            if controllable {
+                let interval = handling.soft_drop_interval(self.update_interval);
+                let timing = (handling.dfr() + interval, interval);
+                self.repeat_held(InputEvent::SoftDrop, (0, 1), &mut held, timing);
+            }
+            self.soft_drop = keys::down(input, &bindings.soft_drop).then_some(held);
+        }
+    }
+
+    // Faint outlines of everywhere the current piece can land, green when it
+    // buries nothing, yellow for one or two new holes and red for more. The one
+    // under the mouse is filled in
+    fn draw_placement_preview(
+        &self,
+        painter: &egui::Painter,
+        response: &egui::Response,
+        block_size: f32,
+    ) {
+        let holes_before = self.board_holes();
+        let cell_rect = |x: usize, y: isize| {
+            egui::Rect::from_min_size(
+                response.rect.min + egui::Vec2::new(x as f32, y as f32) * block_size,
+                egui::Vec2::splat(block_size),
+            )
+        };
+        let spots: Vec<(Vec<egui::Rect>, Color32)> = ai::drop_spots(self)
+            .into_iter()
+            .map(|(placement, shape)| {
+                let mut y = self.current_piece.y;
+                while !self.shape_collides(&shape, placement.x, y + 1) {
+                    y += 1;
+                }
+                let mut cells = Vec::new();
+                for (dy, row) in shape.iter().enumerate() {
+                    for (dx, &cell) in row.iter().enumerate() {
+                        if cell {
+                            cells.push(cell_rect(placement.x + dx, y + dy as isize));
+                        }
+                    }
+                }
+                let new_holes = self
+                    .holes_after_drop(&shape, placement.x, y)
+                    .saturating_sub(holes_before);
+                let color = match new_holes {
+                    0 => Color32::GREEN,
+                    1..=2 => Color32::YELLOW,
+                    _ => Color32::RED,
+                };
+                (cells, color)
+            })
+            .collect();
+        let hovered = response.hover_pos().and_then(|pointer| {
+            spots
+                .iter()
+                .position(|(cells, _)| cells.iter().any(|cell| cell.contains(pointer)))
+        });
+        for (i, (cells, color)) in spots.iter().enumerate() {
+            let outline = egui::Stroke::new(1.0, color.gamma_multiply(0.3));
+            for &cell in cells {
+                if Some(i) == hovered {
+                    painter.rect_filled(cell, 0.0, color.gamma_multiply(0.4));
+                } else {
+                    painter.rect_stroke(cell.shrink(1.0), 0.0, outline);
+                }
+            }
+        }
+    }
+
+    fn draw_isometric(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let (sin, cos) = std::f32::consts::FRAC_PI_6.sin_cos();
+        let (w, h) = (BOARD_WIDTH as f32, BOARD_HEIGHT as f32);
+        let unit = (rect.width() / ((w + h) * cos)).min(rect.height() / ((w + h) * sin + 1.0));
+        let lift = egui::Vec2::new(0.0, unit * 0.8);
+        let origin = rect.center()
+            + egui::Vec2::new(-(w - h) * cos / 2.0, -(w + h) * sin / 2.0) * unit
+            + lift / 2.0;
+        let project =
+            |x: f32, y: f32| origin + egui::Vec2::new((x - y) * cos, (x + y) * sin) * unit;
+
+        painter.add(egui::Shape::convex_polygon(
+            vec![
+                project(0.0, 0.0),
+                project(w, 0.0),
+                project(w, h),
+                project(0.0, h),
+            ],
+            self.theme.background,
+            egui::Stroke::NONE,
+        ));
+        if self.config.show_grid && self.theme.grid_thickness > 0.0 {
+            let grid = self.theme.grid_stroke();
+            for x in 0..=BOARD_WIDTH {
+                painter.line_segment([project(x as f32, 0.0), project(x as f32, h)], grid);
+            }
+            for y in 0..=BOARD_HEIGHT {
+                painter.line_segment([project(0.0, y as f32), project(w, y as f32)], grid);
+            }
+        }
+
+        let mut blocks = Vec::new();
+        for (y, row) in self.board.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if let Some(color) = self.theme.cell_color(cell) {
+                    blocks.push((x, y as isize, color));
+                }
+            }
+        }
+        if !self.game_over {
+            let piece = &self.current_piece;
+            for (dy, row) in piece.shape.iter().enumerate() {
+                for (dx, &cell) in row.iter().enumerate() {
+                    if cell {
+                        blocks.push((
+                            piece.x + dx,
+                            piece.y + dy as isize,
+                            self.theme.piece_color(piece.kind),
+                        ));
+                    }
+                }
+            }
+        }
+        // Back to front, so nearer blocks cover the ones behind them
+        blocks.sort_by_key(|&(x, y, _)| x as isize + y);
+        for (x, y, color) in blocks {
+            let (x, y) = (x as f32, y as f32);
+            let faces = [
+                (
+                    [project(x, y + 1.0), project(x + 1.0, y + 1.0)],
+                    scale_brightness(color, 0.7),
+                ),
+                (
+                    [project(x + 1.0, y), project(x + 1.0, y + 1.0)],
+                    scale_brightness(color, 0.5),
+                ),
+            ];
+            for ([a, b], color) in faces {
+                painter.add(egui::Shape::convex_polygon(
+                    vec![a - lift, b - lift, b, a],
+                    color,
+                    egui::Stroke::NONE,
+                ));
+            }
+            let top = [
+                project(x, y),
+                project(x + 1.0, y),
+                project(x + 1.0, y + 1.0),
+                project(x, y + 1.0),
+            ];
+            painter.add(egui::Shape::convex_polygon(
+                top.map(|p| p - lift).to_vec(),
+                egui::lerp(egui::Rgba::from(color)..=egui::Rgba::WHITE, 0.3),
+                egui::Stroke::NONE,
+            ));
+        }
+    }
+
+    // A dot per rewind, hollow once it has been used
+    fn draw_rewind_tokens(&self, ui: &mut egui::Ui, t: &Locale) {
+        let color = ui.visuals().text_color();
+        for token in 0..REWIND_TOKENS {
+            let (rect, response) = ui.allocate_exact_size(
+                egui::Vec2::splat(REWIND_TOKEN_RADIUS * 2.5),
+                egui::Sense::hover(),
+            );
+            if token < self.rewind_tokens {
+                ui.painter()
+                    .circle_filled(rect.center(), REWIND_TOKEN_RADIUS, color);
+            } else {
+                ui.painter().circle_stroke(
+                    rect.center(),
+                    REWIND_TOKEN_RADIUS,
+                    egui::Stroke::new(1.0, color),
+                );
+            }
+            response.on_hover_text(t.get("rewind_hint"));
+        }
+    }
+
+    // A banner along the top for each warning, with a button to dismiss it
+    fn draw_warnings(&mut self, ctx: &egui::Context) {
+        if self.warnings.is_empty() {
+            return;
+        }
+        let t = locale(self.config.language);
+        let mut dismissed = None;
+        egui::TopBottomPanel::top("warnings").show(ctx, |ui| {
+            for (index, warning) in self.warnings.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                    if ui.small_button(t.get("dismiss")).clicked() {
+                        dismissed = Some(index);
+                    }
+                });
+            }
+        });
+        if let Some(index) = dismissed {
+            self.warnings.remove(index);
+        }
+    }
+
+    // Kept free above and left of the board for Config::show_axis_labels, as
+    // wide as the longest row number
+    fn axis_label_space(ctx: &egui::Context) -> f32 {
+        let widest = (BOARD_HEIGHT - 1).to_string();
+        let font = egui::FontId::monospace(AXIS_LABEL_SIZE);
+        let size = ctx.fonts(|fonts| fonts.layout_no_wrap(widest, font, Color32::WHITE).size());
+        size.x.max(size.y) + AXIS_LABEL_GAP
+    }
+
+    // Column numbers above the board and row numbers to its left, as the board
+    // and puzzle definitions count them
+    fn draw_axis_labels(
+        &self,
+        painter: &egui::Painter,
+        (board, space): (egui::Rect, f32),
+        block_size: f32,
+    ) {
+        let font = egui::FontId::monospace(AXIS_LABEL_SIZE);
+        let color = Color32::from(self.theme.grid);
+        for x in 0..BOARD_WIDTH {
+            painter.text(
+                egui::pos2(
+                    board.left() + (x as f32 + 0.5) * block_size,
+                    board.top() - space / 2.0,
+                ),
+                egui::Align2::CENTER_CENTER,
+                x.to_string(),
+                font.clone(),
+                color,
+            );
+        }
+        for y in 0..BOARD_HEIGHT {
+            painter.text(
+                egui::pos2(
+                    board.left() - AXIS_LABEL_GAP,
+                    board.top() + (y as f32 + 0.5) * block_size,
+                ),
+                egui::Align2::RIGHT_CENTER,
+                y.to_string(),
+                font.clone(),
+                color,
+            );
+        }
+    }
+
+    // One bar per level stacked up beside the board, the current one pulsing
+    fn draw_level_meter(
+        &self,
+        ctx: &egui::Context,
+        (meter, painter): (egui::Response, egui::Painter),
+    ) {
+        let bar_height = meter.rect.height() / LEVEL_METER_BARS as f32;
+        let pulse = if self.config.reduced_motion {
+            1.0
+        } else {
+            0.7 + 0.3 * (ctx.input(|i| i.time) as f32 * 6.0).sin()
+        };
+        for i in 0..LEVEL_METER_BARS {
+            let bottom = meter.rect.bottom() - i as f32 * bar_height;
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(meter.rect.left(), bottom - bar_height + 1.0),
+                egui::pos2(meter.rect.right(), bottom - 1.0),
+            );
+            let level = i + 1;
+            let color = if level > self.level {
+                Color32::from(self.theme.grid).gamma_multiply(0.3)
+            } else {
+                let t = i as f32 / (LEVEL_METER_BARS - 1) as f32;
+                let (from, to, t) = if t < 0.5 {
+                    (
+                        Color32::from_rgb(0, 200, 0),
+                        Color32::from_rgb(255, 165, 0),
+                        t * 2.0,
+                    )
+                } else {
+                    (
+                        Color32::from_rgb(255, 165, 0),
+                        Color32::from_rgb(220, 0, 0),
+                        t * 2.0 - 1.0,
+                    )
+                };
+                let color: Color32 =
+                    egui::lerp(egui::Rgba::from(from)..=egui::Rgba::from(to), t).into();
+                // Past the top bar, the top bar stands for the current level
+                if level == self.level.min(LEVEL_METER_BARS) {
+                    color.gamma_multiply(pulse)
+                } else {
+                    color
+                }
+            };
+            painter.rect_filled(bar, 2.0, color);
+        }
+    }
+
+    // A small drawing of a piece beside the board, or an empty box for None
+    fn draw_preview(
+        &self,
+        ui: &mut egui::Ui,
+        piece: Option<(TetrominoType, Color32)>,
+    ) -> egui::Rect {
+        let preview_block = BLOCK_SIZE * 0.6;
+        let (preview, preview_painter) = ui.allocate_painter(
+            egui::Vec2::new(4.0 * preview_block, 2.0 * preview_block),
+            egui::Sense::hover(),
+        );
+        let Some((kind, color)) = piece else {
+            return preview.rect;
+        };
+        for (dy, row) in kind.shape().iter().enumerate() {
+            for (dx, &cell) in row.iter().enumerate() {
+                if cell {
+                    preview_painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            preview.rect.min
+                                + egui::Vec2::new(
+                                    dx as f32 * preview_block,
+                                    dy as f32 * preview_block,
+                                ),
+                            egui::Vec2::splat(preview_block),
+                        ),
+                        0.0,
+                        color,
+                    );
+                }
+            }
+        }
+        preview.rect
+    }
+
+    // Upcoming pieces top to bottom, with a dashed seam above any that opened a
+    // fresh 7-bag
+    fn draw_previews(&self, ui: &mut egui::Ui, pieces: &[(TetrominoType, bool)]) {
+        let t = locale(self.config.language);
+        for &(kind, opens_bag) in pieces {
+            let preview = self.draw_preview(ui, Some((kind, self.theme.piece_color(kind))));
+            // Names the piece for anyone who can't tell it by color, or doesn't know it yet
+            if self.config.show_piece_labels {
+                let text = format!("{kind:?} {}", t.get(self.theme.piece_color_name(kind)));
+                ui.add(egui::Label::new(
+                    egui::RichText::new(text).font(egui::FontId::monospace(9.0)),
+                ));
+            }
+            if opens_bag {
+                // Drawn in the gap above the preview, outside its clip rect
+                let y = preview.top() - ui.spacing().item_spacing.y / 2.0;
+                ui.painter().extend(egui::Shape::dashed_line(
+                    &[
+                        egui::pos2(preview.left(), y),
+                        egui::pos2(preview.right(), y),
+                    ],
+                    egui::Stroke::new(1.0, self.theme.grid),
+                    4.0,
+                    3.0,
+                ));
+            }
+        }
+    }
+
+    // Hands queued announcements to egui, which passes them on to the screen
+    // reader through the platform's accessibility integration
+    fn announce_events(&mut self, ctx: &egui::Context) {
+        ctx.options_mut(|options| options.screen_reader = self.config.screen_reader);
+        let announcements = std::mem::take(&mut self.announcements);
+        if !self.config.screen_reader {
+            return;
+        }
+        ctx.output_mut(|output| {
+            for text in announcements {
+                output.events.push(egui::output::OutputEvent::ValueChanged(
+                    egui::WidgetInfo::labeled(egui::WidgetType::Label, text),
+                ));
+            }
+        });
+    }
+
+    // Picking a preset fills in the timings, and changing any timing makes it Custom
+    fn draw_handling_settings(&mut self, ui: &mut egui::Ui, t: &Locale) {
+        let mut preset = self.config.handling_preset;
+        egui::ComboBox::from_label(t.get("handling_preset"))
+            .selected_text(format!("{:?}", preset))
+            .show_ui(ui, |ui| {
+                for option in HandlingPreset::ALL {
+                    ui.selectable_value(&mut preset, option, format!("{:?}", option));
+                }
+            });
+        if preset != self.config.handling_preset {
+            self.config.handling_preset = preset;
+            if let Some(handling) = preset.handling() {
+                self.config.handling = handling;
+            }
+        }
+        let handling = &mut self.config.handling;
+        let changed = [
+            ui.add(egui::Slider::new(&mut handling.das_delay_ms, 0..=500).text(t.get("das"))),
+            ui.add(egui::Slider::new(&mut handling.arr_ms, 0..=200).text(t.get("arr"))),
+            ui.add(egui::Slider::new(&mut handling.sdf, 1..=40).text(t.get("sdf"))),
+            ui.add(egui::Slider::new(&mut handling.dfr_ms, 0..=200).text(t.get("dfr"))),
+        ]
+        .iter()
+        .any(|response| response.changed());
+        if changed {
+            self.config.handling_preset = HandlingPreset::Custom;
+        }
+    }
+
+    fn draw_high_scores(&mut self, ui: &mut egui::Ui, t: &Locale) {
+        if self.config.high_scores.is_empty() {
+            ui.label(t.get("no_high_scores"));
+            return;
+        }
+        if ui.button(t.get("export_csv")).clicked() {
+            self.export_high_scores();
+        }
+        match self.scores_export.lock().unwrap().take() {
+            Some(Ok(path)) => self.scores_exported = Some(path),
+            Some(Err(err)) => self.warnings.push(format!("{err:#}")),
+            None => {}
+        }
+        if let Some(path) = &self.scores_exported {
+            ui.label(t.fill("scores_exported", &[&path.display()]));
+        }
+        egui::Grid::new("high_scores").striped(true).show(ui, |ui| {
+            // Clicking a header sorts on that column
+            for column in SortBy::ALL {
+                let sorted = self.config.high_score_sort == column;
+                if ui.selectable_label(sorted, t.get(column.label())).clicked() {
+                    self.config.high_score_sort = column;
+                }
+            }
+            ui.end_row();
+            for entry in self
+                .config
+                .high_scores
+                .sorted_by(self.config.high_score_sort)
+            {
+                ui.label(entry.score.to_string());
+                ui.label(entry.level.to_string());
+                ui.label(entry.lines.to_string());
+                ui.label(format!("{:.0}%", entry.efficiency * 100.0));
+                ui.label(format!("{:.1}", entry.lines_per_minute));
+                ui.label(entry.finesse_errors.to_string());
+                ui.end_row();
+            }
+        });
+    }
+
+    fn draw_end_screen(&mut self, ctx: &egui::Context, t: &Locale) {
+        let Some(screen) = &mut self.end_screen else {
+            return;
+        };
+        let grade = self.challenge.as_ref().map(|challenge| {
+            challenge::grade(challenge.target_lines, self.lines_cleared, self.pieces_used)
+        });
+        let (mut play_again, mut main_menu, mut save_replay) = (false, false, false);
+        egui::Window::new(t.get("game_over"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if let Some(grade) = grade {
+                    ui.heading(t.fill("challenge_over", &[&grade]));
+                }
+                let new_best = |ui: &mut egui::Ui, best: bool| {
+                    if best {
+                        ui.colored_label(Color32::GOLD, t.get("new_best"));
+                    }
+                };
+                ui.horizontal(|ui| {
+                    ui.heading(t.fill(
+                        "score",
+                        &[&screen.displayed_score(self.config.reduced_motion)],
+                    ));
+                    new_best(ui, screen.score_best);
+                });
+                egui::Grid::new("end_screen_stats")
+                    .num_columns(3)
+                    .spacing([16.0, 4.0])
+                    .show(ui, |ui| {
+                        for stat in &screen.stats {
+                            ui.label(t.get(stat.label));
+                            ui.monospace(&stat.value);
+                            new_best(ui, stat.new_best);
+                            ui.end_row();
+                        }
+                    });
+                if screen.buttons_ready() {
+                    ui.horizontal(|ui| {
+                        play_again = ui.button(t.get("play_again")).clicked();
+                        main_menu = ui.button(t.get("return_to_menu")).clicked();
+                        match &screen.saved_replay {
+                            Some(path) => {
+                                ui.label(t.fill("replay_saved", &[&path.display()]));
+                            }
+                            None => save_replay = ui.button(t.get("save_replay")).clicked(),
+                        }
+                    });
+                }
+            });
+        if save_replay {
+            match self.save_replay() {
+                Ok(path) => {
+                    if let Some(screen) = &mut self.end_screen {
+                        screen.saved_replay = Some(path);
+                    }
+                }
+                Err(err) => self
+                    .warnings
+                    .push(format!("{:#}", err.context("failed to save the replay"))),
+            }
+        }
+        if main_menu {
+            // Back to a plain local game, away from any opponent, challenge or walkthrough
+            self.network = None;
+            self.challenge = None;
+            self.mode = GameMode::Marathon;
+        }
+        if play_again || main_menu {
+            self.restart();
+        }
+    }
+
+    // The save dialog runs on its own thread so the game keeps drawing behind it
+    fn export_high_scores(&self) {
+        let table = self.config.high_scores.clone();
+        let result = self.scores_export.clone();
+        std::thread::spawn(move || {
+            let dialog = rfd::AsyncFileDialog::new()
+                .set_file_name("scores.csv")
+                .add_filter("CSV", &["csv"])
+                .save_file();
+            let Some(file) = pollster::block_on(dialog) else {
+                return;
+            };
+            let path = file.path().to_path_buf();
+            let written = table.export_csv(&path).map(|()| path);
+            *result.lock().unwrap() = Some(written);
+        });
+    }
+
+    fn set_fullscreen(&mut self, frame: &mut eframe::Frame, fullscreen: bool) {
+        if fullscreen {
+            self.windowed_size = Some(frame.info().window_info.size);
+        } else if let Some(size) = self.windowed_size.take() {
+            frame.set_window_size(size);
+        }
+        frame.set_fullscreen(fullscreen);
+        self.fullscreen = fullscreen;
+    }
+}
+
+// Darkens a color for factors below 1 and brightens it above, capped at
+// white, without touching its alpha
+fn scale_brightness(color: Color32, factor: f32) -> Color32 {
+    let [r, g, b, a] = color.to_array();
+    let scale = |channel: u8| (channel as f32 * factor) as u8;
+    Color32::from_rgba_premultiplied(scale(r), scale(g), scale(b), a)
+}
+
+impl eframe::App for TetrisGame {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.draw_warnings(ctx);
+        if let Some(lobby) = &mut self.lobby {
+            lobby.update(ctx, frame);
+            let outcome = lobby.outcome();
+            self.config.username = lobby.username.clone();
+            match outcome {
+                Some(LobbyOutcome::Start(link)) => {
+                    self.lobby = None;
+                    self.network = Some(link);
+                    self.restart();
+                }
+                Some(LobbyOutcome::Leave) => {
+                    self.lobby = None;
+                    self.network = None;
+                    self.restart();
+                }
+                None => {}
+            }
+            return;
+        }
+
+        if let Some(four_player) = &mut self.four_player {
+            four_player.update(ctx, frame);
+            if four_player.left() {
+                self.four_player = None;
+            }
+            return;
+        }
+
+        self.theme = if self.config.adaptive_theme {
+            Theme::system_adaptive(ctx)
+        } else {
+            self.config.theme.theme()
+        };
+        self.theme.pattern = self.config.background_pattern;
+        self.theme.piece_shadow = self.config.piece_shadow;
+        self.theme.style = self.config.render_style;
+        self.theme.set_grid_style(self.config.grid_style);
+        if ctx.input(|i| {
+            i.events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Key { pressed: true, .. }))
+        }) {
+            self.tutorial.dismiss();
+        }
+        if !self.ai_mode {
+            let primary = self.config.rotation_system.primary();
+            let bindings = &self.config.key_bindings;
+            let rotation = ctx.input(|i| {
+                if keys::down(i, &bindings.rotate) {
+                    Some(primary)
+                } else if keys::down(i, &bindings.rotate_reverse) {
+                    Some(primary.reversed())
+                } else {
+                    None
+                }
+            });
+            let hold = ctx.input(|i| keys::down(i, &bindings.hold));
+            self.queue_spawn_keys(rotation, hold);
+        }
+        let dt = ctx.input(|i| i.stable_dt);
+        self.update();
+        if !self.paused && !self.game_over {
+            self.stats.play_time += Duration::from_secs_f32(dt);
+            self.record_rewind_point();
+        }
+        self.animate(dt);
+
+        let t = locale(self.config.language);
+        let title = if self.game_over {
+            t.get("title_game_over").to_string()
+        } else if self.paused {
+            t.get("title_paused").to_string()
+        } else {
+            t.fill(
+                "title_playing",
+                &[&self.score, &self.level, &self.lines_cleared],
+            )
+        };
+        if title != self.window_title {
+            frame.set_window_title(&title);
+            self.window_title = title;
+        }
+
+        egui::SidePanel::right("move_log").show_animated(ctx, self.show_move_log, |ui| {
+            ui.heading(t.get("move_log"));
+            ui.horizontal_wrapped(|ui| {
+                for event in InputEvent::ALL {
+                    let mut shown = !self.move_log_hidden.contains(&event);
+                    if ui.checkbox(&mut shown, format!("{:?}", event)).changed() {
+                        if shown {
+                            self.move_log_hidden.retain(|&hidden| hidden != event);
+                        } else {
+                            self.move_log_hidden.push(event);
+                        }
+                    }
+                }
+            });
+            let visible: Vec<&String> = self
+                .move_log
+                .iter()
+                .filter(|(event, _)| !self.move_log_hidden.contains(event))
+                .map(|(_, entry)| entry)
+                .collect();
+            if ui.button(t.get("copy_log")).clicked() {
+                let text = visible
+                    .iter()
+                    .map(|entry| entry.as_str())
+                    .collect::<Vec<_>>();
+                ui.output_mut(|output| output.copied_text = text.join("\n"));
+            }
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for entry in visible {
+                        ui.monospace(entry);
+                    }
+                });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let (GameMode::Benchmark, Some(run)) = (self.mode, &self.benchmark) {
+                ui.heading(t.get("benchmark"));
+                let result = run.result.lock().unwrap().clone();
+                match result {
+                    Some(result) => {
+                        ui.label(t.fill("games", &[&result.games]));
+                        ui.label(t.fill(
+                            "decisions_per_second",
+                            &[&format!("{:.0}", result.decisions_per_second)],
+                        ));
+                        ui.label(
+                            t.fill("average_score", &[&format!("{:.0}", result.average_score)]),
+                        );
+                        ui.label(
+                            t.fill("average_lines", &[&format!("{:.1}", result.average_lines)]),
+                        );
+                    }
+                    None => {
+                        let finished = run.finished_games.load(Ordering::Relaxed);
+                        ui.label(
+                            t.fill("benchmark_progress", &[&(finished + 1), &BENCHMARK_GAMES]),
+                        );
+                    }
+                }
+                if ui.button(t.get("back_to_game")).clicked() {
+                    self.restart();
+                }
+                return;
+            }
+
+            if let Some(NetworkStatus::Disconnected(reason)) =
+                self.network.as_ref().map(|network| network.status())
+            {
+                ui.heading(t.get("connection_lost"));
+                ui.label(reason);
+                if ui.button(t.get("return_to_menu")).clicked() {
+                    self.network = None;
+                    self.restart();
+                }
+                return;
+            }
+
+            ui.heading(t.get("heading"));
+            if self.network.is_some() {
+                ui.label(t.get("online_connected"));
+            }
+            if self.mode == GameMode::DailyChallenge {
+                let left = daily::until_next(SystemTime::now()).as_secs();
+                ui.label(t.fill(
+                    "daily_challenge",
+                    &[&format!(
+                        "{:02}:{:02}:{:02}",
+                        left / 3600,
+                        left / 60 % 60,
+                        left % 60
+                    )],
+                ));
+                if let Some(best) = self.config.high_scores.daily_best.on(self.daily_day) {
+                    ui.label(t.fill("daily_best", &[&best]));
+                }
+            }
+            match (self.crown(), &self.opponent) {
+                (Some(Crown::Ours), _) => {
+                    ui.colored_label(CROWN_COLOR, t.fill("crown_ours", &[&KING_OF_THE_HILL_POWER]));
+                }
+                (Some(Crown::Theirs), Some(opponent)) => {
+                    ui.colored_label(CROWN_COLOR, t.fill("crown_theirs", &[&opponent.username]));
+                }
+                _ => {}
+            }
+            if self.mode == GameMode::Manual && self.stats.pieces < MANUAL_HINT_PIECES {
+                ui.label(t.get("manual_hint"));
+            }
+            if self.mode == GameMode::TrainingWheels {
+                ui.label(t.get(if self.awaiting_placement() {
+                    "training_wheels_place"
+                } else {
+                    "training_wheels_hint"
+                }));
+            }
+            if let Some(challenge) = &self.challenge {
+                ui.label(
+                    egui::RichText::new(t.fill(
+                        "challenge_progress",
+                        &[
+                            &challenge.name,
+                            &self.lines_cleared.min(challenge.target_lines),
+                            &challenge.target_lines,
+                            &self.pieces_used,
+                            &challenge.max_pieces,
+                        ],
+                    ))
+                    .size(18.0)
+                    .strong(),
+                );
+            }
+            ui.horizontal(|ui| {
+                ui.label(t.fill("score", &[&(self.displayed_score as u32)]));
+                if self.rewind_allowed() {
+                    self.draw_rewind_tokens(ui, t);
+                }
+            });
+            if let Some(active) = &self.events.active {
+                ui.add(
+                    egui::ProgressBar::new(active.remaining().max(0.0))
+                        .desired_width(BOARD_WIDTH as f32 * BLOCK_SIZE)
+                        .text(t.fill("multiplier", &[&active.multiplier])),
+                );
+            }
+            ui.label(t.fill("level", &[&self.level]));
+            match self.level_up_at {
+                Some(at) if at.elapsed() < LEVEL_UP_FLASH_DURATION => {
+                    ui.colored_label(Color32::YELLOW, t.get("level_up"));
+                }
+                _ => {
+                    let remaining = LINES_PER_LEVEL - self.lines_cleared % LINES_PER_LEVEL;
+                    let text = t.fill("next_level", &[&remaining]);
+                    if remaining == 1 {
+                        ui.colored_label(Color32::YELLOW, text);
+                    } else {
+                        ui.label(text);
+                    }
+                }
+            }
+
+            let previews: Vec<_> = self
+                .bag
+                .preview(usize::from(self.config.preview_count))
+                .collect();
+            ui.horizontal(|ui| {
+                ui.vertical(|ui| {
+                    ui.label(t.get("hold"));
+                    let color = self.held_piece.map(|kind| {
+                        let color = self.theme.piece_color(kind);
+                        if self.can_hold {
+                            color
+                        } else {
+                            color.gamma_multiply(0.4)
+                        }
+                    });
+                    self.draw_preview(ui, self.held_piece.zip(color));
+                });
+                // A single preview fits in a compact box beside Hold
+                if previews.len() == 1 {
+                    ui.vertical(|ui| {
+                        ui.label(t.get("next"));
+                        self.draw_previews(ui, &previews);
+                    });
+                }
+            });
+
+            let label_space = if self.config.show_axis_labels && !self.isometric {
+                Self::axis_label_space(ctx)
+            } else {
+                0.0
+            };
+            // Fullscreen scales the board to the space left, centered with letterboxing
+            let block_size = if self.fullscreen {
+                let available = ui.available_size() - egui::Vec2::splat(label_space);
+                (available.x / BOARD_WIDTH as f32).min(available.y / BOARD_HEIGHT as f32)
+            } else {
+                BLOCK_SIZE
+            };
+            let board_size = egui::Vec2::new(
+                BOARD_WIDTH as f32 * block_size,
+                BOARD_HEIGHT as f32 * block_size,
+            );
+            let (level_meter, (mut response, mut painter)) = ui
+                .horizontal(|ui| {
+                    let meter_width = LEVEL_METER_WIDTH + ui.spacing().item_spacing.x;
+                    ui.add_space(
+                        ((ui.available_width() - board_size.x - label_space) / 2.0 - meter_width)
+                            .max(0.0),
+                    );
+                    // Both grow by the labels' space, then give it back so the
+                    // meter still lines up with the board
+                    let mut meter = ui.allocate_painter(
+                        egui::Vec2::new(LEVEL_METER_WIDTH, board_size.y + label_space),
+                        egui::Sense::hover(),
+                    );
+                    meter.0.rect.min.y += label_space;
+                    let (mut board, board_painter) = ui.allocate_painter(
+                        board_size + egui::Vec2::splat(label_space),
+                        egui::Sense::click_and_drag(),
+                    );
+                    board.rect.min += egui::Vec2::splat(label_space);
+                    if label_space > 0.0 {
+                        let labels = (board.rect, label_space);
+                        self.draw_axis_labels(&board_painter, labels, block_size);
+                    }
+                    let clip = board_painter.clip_rect().intersect(board.rect);
+                    let board = (board, board_painter.with_clip_rect(clip));
+                    // Longer queues go in a sidebar that grows a column every few pieces
+                    if previews.len() > 1 {
+                        ui.vertical(|ui| {
+                            ui.label(t.get("next"));
+                            ui.horizontal(|ui| {
+                                for column in previews.chunks(PREVIEW_COLUMN_LENGTH) {
+                                    ui.vertical(|ui| self.draw_previews(ui, column));
+                                }
+                            });
+                        });
+                    }
+                    (meter, board)
+                })
+                .inner;
+            self.draw_level_meter(ctx, level_meter);
+
+            // A stack nearing the top makes the board shake side to side
+            let amplitude = self.shake_amplitude();
+            if amplitude > 0.0 {
+                let time = ctx.input(|i| i.time) as f32;
+                response.rect = response
+                    .rect
+                    .translate(egui::vec2((time * 20.0).sin() * amplitude, 0.0));
+                // Wide enough for the shake, but never past the panel
+                painter.set_clip_rect(
+                    response
+                        .rect
+                        .expand2(egui::vec2(SHAKE_MAX_AMPLITUDE, 0.0))
+                        .intersect(ui.clip_rect()),
+                );
+            }
+
+            if self.game_over && self.game_over_anim.is_none() {
+                self.finish_game();
+            }
+            // The flashing row brightens toward white, then empties and the one above starts
+            let mut flash = None;
+            if let Some(anim) = &mut self.game_over_anim {
+                if anim.current_row >= 0 && anim.last_clear.elapsed() >= GAME_OVER_CLEAR_STEP {
+                    self.board[anim.current_row as usize] = vec![CellState::Empty; BOARD_WIDTH];
+                    anim.current_row -= 1;
+                    anim.last_clear = Instant::now();
+                }
+                if anim.current_row >= 0 {
+                    let progress = anim.last_clear.elapsed().as_secs_f32()
+                        / GAME_OVER_CLEAR_STEP.as_secs_f32();
+                    flash = Some((anim.current_row as usize, progress.min(1.0)));
+                }
+            }
+
+            if self.isometric {
+                self.draw_isometric(&painter, response.rect);
+            } else {
+                painter.rect_filled(response.rect, 0.0, self.theme.background);
+                if let Some(texture) = &self.background_image {
+                    painter.image(
+                        texture.id(),
+                        response.rect,
+                        cover_uv(texture.size_vec2(), response.rect.size()),
+                        Color32::WHITE,
+                    );
+                    painter.rect_filled(
+                        response.rect,
+                        0.0,
+                        Color32::from_black_alpha(BACKGROUND_IMAGE_DIMMING),
+                    );
+                }
+                self.theme
+                    .paint_pattern(&painter, response.rect, block_size);
+
+                // The grid
+                if self.config.show_grid && self.theme.grid_thickness > 0.0 {
+                    for x in 0..=BOARD_WIDTH {
+                        painter.line_segment(
+                            [
+                                response.rect.min + egui::Vec2::new(x as f32 * block_size, 0.0),
+                                response.rect.min
+                                    + egui::Vec2::new(
+                                        x as f32 * block_size,
+                                        BOARD_HEIGHT as f32 * block_size,
+                                    ),
+                            ],
+                            self.theme.grid_stroke(),
+                        );
+                    }
+                    for y in 0..=BOARD_HEIGHT {
+                        painter.line_segment(
+                            [
+                                response.rect.min + egui::Vec2::new(0.0, y as f32 * block_size),
+                                response.rect.min
+                                    + egui::Vec2::new(
+                                        BOARD_WIDTH as f32 * block_size,
+                                        y as f32 * block_size,
+                                    ),
+                            ],
+                            self.theme.grid_stroke(),
+                        );
+                    }
+                }
+
+                // The band of rows the piece covers, or will land in while its
+                // drop trajectory is showing. A hard drop goes on the press, so the
+                // trajectory only shows by itself while one waits to be confirmed
+                let show_trajectory = self.config.drop_preview
+                    || self
+                        .hard_drop_pending
+                        .is_some_and(|asked| asked.elapsed() < HARD_DROP_CONFIRM_WINDOW);
+                if self.config.show_active_row_highlight && !self.game_over {
+                    let top = if show_trajectory {
+                        self.ghost_y()
+                    } else {
+                        self.current_piece.y
+                    };
+                    let height = self
+                        .current_piece
+                        .shape
+                        .len()
+                        .min((BOARD_HEIGHT as isize - top) as usize);
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            response.rect.min + egui::Vec2::new(0.0, top as f32 * block_size),
+                            egui::Vec2::new(
+                                BOARD_WIDTH as f32 * block_size,
+                                height as f32 * block_size,
+                            ),
+                        ),
+                        0.0,
+                        Color32::from(self.theme.grid).gamma_multiply(0.06),
+                    );
+                }
+
+                // The board
+                let lock_flash = self.locked_cells_flash.as_ref().map(|(cells, _)| cells);
+                let mut cells = Vec::new();
+                for (y, row) in self.board.iter().enumerate() {
+                    for (x, &cell) in row.iter().enumerate() {
+                        if let Some(mut color) = self.theme.cell_color(cell) {
+                            if lock_flash.is_some_and(|cells| cells.contains(&(x, y))) {
+                                color = scale_brightness(color, LOCK_FLASH_BRIGHTNESS);
+                            }
+                            let color = match flash {
+                                Some((row, progress)) if row == y => egui::lerp(
+                                    egui::Rgba::from(color)..=egui::Rgba::WHITE,
+                                    progress,
+                                )
+                                .into(),
+                                _ => color,
+                            };
+                            let rect = egui::Rect::from_min_size(
+                                response.rect.min
+                                    + egui::Vec2::new(x as f32 * block_size, y as f32 * block_size),
+                                egui::Vec2::splat(block_size),
+                            );
+                            self.theme.paint_glow(&painter, corners(rect), color);
+                            cells.push((rect, color));
+                        }
+                    }
+                }
+                for (rect, color) in cells {
+                    painter.rect_filled(rect, 0.0, color);
+                }
+
+                // Debug mode labels empty cells with their coordinates and filled
+                // ones with what they hold, as long as the cells are big enough to read
+                if self.debug_overlay && block_size >= CELL_LABEL_MIN_BLOCK {
+                    let font = egui::FontId::proportional(8.0);
+                    let grid = Color32::from(self.theme.grid).gamma_multiply(0.6);
+                    for (y, row) in self.board.iter().enumerate() {
+                        for (x, &cell) in row.iter().enumerate() {
+                            let (text, color) = match cell {
+                                CellState::Empty => (format!("({x},{y})"), grid),
+                                CellState::Filled(kind) => {
+                                    (format!("{kind:?}"), self.theme.background.into())
+                                }
+                                CellState::Garbage => {
+                                    ("G".to_string(), self.theme.background.into())
+                                }
+                            };
+                            painter.text(
+                                response.rect.min
+                                    + egui::Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * block_size,
+                                egui::Align2::CENTER_CENTER,
+                                text,
+                                font.clone(),
+                                color,
+                            );
+                        }
+                    }
+                }
+
+                // Only over empty cells, so it never covers the piece that made it
+                for &(x, y, passed) in &self.trail {
+                    if self.board[y][x].is_filled() {
+                        continue;
+                    }
+                    let fade = 1.0 - passed.elapsed().as_secs_f32() / TRAIL_DURATION.as_secs_f32();
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            response.rect.min
+                                + egui::Vec2::new(x as f32 * block_size, y as f32 * block_size),
+                            egui::Vec2::splat(block_size),
+                        ),
+                        0.0,
+                        Color32::from_white_alpha((100.0 * fade.max(0.0)) as u8),
+                    );
+                }
+
+                for glow in &self.glow_cells {
+                    let fade =
+                        1.0 - glow.born.elapsed().as_secs_f32() / GLOW_DURATION.as_secs_f32();
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            response.rect.min
+                                + egui::Vec2::new(
+                                    glow.x as f32 * block_size,
+                                    glow.y as f32 * block_size,
+                                ),
+                            egui::Vec2::splat(block_size),
+                        )
+                        .shrink(3.0),
+                        2.0,
+                        Color32::from_white_alpha((200.0 * fade.max(0.0)) as u8),
+                    );
+                }
+
+                for feedback in &self.placement_feedbacks {
+                    let fade = 1.0
+                        - feedback.born.elapsed().as_secs_f32()
+                            / PLACEMENT_FEEDBACK_DURATION.as_secs_f32();
+                    for &(x, y) in &feedback.cells {
+                        painter.rect_filled(
+                            egui::Rect::from_min_size(
+                                response.rect.min
+                                    + egui::Vec2::new(x as f32 * block_size, y as f32 * block_size),
+                                egui::Vec2::splat(block_size),
+                            ),
+                            0.0,
+                            feedback.color.gamma_multiply(fade.max(0.0)),
+                        );
+                    }
+                }
+
+                if self.placement_preview && !self.game_over {
+                    self.draw_placement_preview(&painter, &response, block_size);
+                }
+
+                // Incoming garbage as a bar up the left edge, one cell per row
+                if self.pending_garbage > 0 {
+                    let rows = self.pending_garbage.min(BOARD_HEIGHT as u32) as f32;
+                    let bar = egui::Rect::from_min_max(
+                        response.rect.left_bottom() - egui::vec2(0.0, rows * block_size),
+                        response.rect.left_bottom() + egui::vec2(PENDING_GARBAGE_BAR_WIDTH, 0.0),
+                    );
+                    painter.rect_filled(bar, 0.0, Color32::RED);
+                }
+
+                // A dot in the top right corner that flashes with gravity. When it
+                // beats only every few ticks, a ring marks it as the slower pulse
+                if self.config.show_metronome
+                    && self.has_gravity()
+                    && !self.paused
+                    && !self.game_over
+                {
+                    let center = response.rect.right_top()
+                        + egui::vec2(-2.0, 2.0) * METRONOME_RADIUS;
+                    let color = Color32::from(self.theme.pieces[0])
+                        .gamma_multiply(self.metronome_alpha(Instant::now()));
+                    painter.circle_filled(center, METRONOME_RADIUS, color);
+                    if self.metronome_ticks_per_beat() > 1 {
+                        painter.circle_stroke(
+                            center,
+                            METRONOME_RADIUS * 1.75,
+                            egui::Stroke::new(1.0, Color32::from(self.theme.pieces[0])),
+                        );
+                    }
+                }
+
+                // The piece that topped out goes away with the board
+                if !self.game_over {
+                    let piece = &self.current_piece;
+                    let piece_color = self.theme.piece_color(piece.kind);
+                    if show_trajectory {
+                        // Faint columns from each of the piece's bottom cells down to where it lands
+                        let ghost_y = self.ghost_y();
+                        for dx in 0..piece.shape[0].len() {
+                            let Some(bottom) = piece.shape.iter().rposition(|row| row[dx]) else {
+                                continue;
+                            };
+                            for y in piece.y + bottom as isize + 1..=ghost_y + bottom as isize {
+                                painter.rect_filled(
+                                    egui::Rect::from_min_size(
+                                        response.rect.min
+                                            + egui::Vec2::new(
+                                                (piece.x + dx) as f32 * block_size,
+                                                y as f32 * block_size,
+                                            ),
+                                        egui::Vec2::splat(block_size),
+                                    ),
+                                    0.0,
+                                    piece_color.gamma_multiply(0.15),
+                                );
+                            }
+                        }
+                    }
+                    let pivot = response.rect.min
+                        + egui::Vec2::new(
+                            (piece.visual_x + piece.shape[0].len() as f32 / 2.0) * block_size,
+                            (piece.visual_y + piece.shape.len() as f32 / 2.0) * block_size,
+                        );
+                    let rotation = piece.rotation_angle().map(egui::emath::Rot2::from_angle);
+                    let mut cells = Vec::new();
+                    for (dy, row) in piece.shape.iter().enumerate() {
+                        for (dx, &cell) in row.iter().enumerate() {
+                            if cell {
+                                let rect = egui::Rect::from_min_size(
+                                    response.rect.min
+                                        + egui::Vec2::new(
+                                            (piece.visual_x + dx as f32) * block_size,
+                                            (piece.visual_y + dy as f32) * block_size,
+                                        ),
+                                    egui::Vec2::splat(block_size),
+                                );
+                                let corners = corners(rect);
+                                cells.push(match rotation {
+                                    Some(rotation) => {
+                                        corners.map(|p| pivot + rotation * (p - pivot))
+                                    }
+                                    None => corners,
+                                });
+                            }
+                        }
+                    }
+                    // All of the shadow goes down first so no cell is drawn over by
+                    // its neighbor's shadow. The board painter clips to the board,
+                    // so the shadow never spills past its edge
+                    if self.theme.piece_shadow {
+                        for corners in &cells {
+                            painter.add(egui::Shape::convex_polygon(
+                                corners.map(|p| p + PIECE_SHADOW_OFFSET).to_vec(),
+                                Color32::from_rgba_unmultiplied(0, 0, 0, 60),
+                                egui::Stroke::NONE,
+                            ));
+                        }
+                    }
+                    for &corners in &cells {
+                        self.theme.paint_glow(&painter, corners, piece_color);
+                    }
+                    for corners in cells {
+                        painter.add(egui::Shape::convex_polygon(
+                            corners.to_vec(),
+                            piece_color,
+                            egui::Stroke::NONE,
+                        ));
+                    }
+                    // Where the piece will lock once the player confirms it
+                    if self.awaiting_placement() {
+                        for y in 0..piece.shape.len() {
+                            for x in (0..piece.shape[y].len()).filter(|&x| piece.shape[y][x]) {
+                                let cell = egui::Rect::from_min_size(
+                                    response.rect.min
+                                        + egui::vec2((piece.x + x) as f32, (piece.y + y as isize) as f32)
+                                            * block_size,
+                                    egui::Vec2::splat(block_size),
+                                );
+                                painter.rect_filled(cell, 0.0, PLACEMENT_HIGHLIGHT);
+                                painter.rect_stroke(
+                                    cell.shrink(1.0),
+                                    0.0,
+                                    egui::Stroke::new(2.0, Color32::YELLOW),
+                                );
+                            }
+                        }
+                    }
+                    // A red dot at the center of mass and a cross where turns pivot
+                    if self.debug_overlay {
+                        let origin = response.rect.min
+                            + egui::vec2(piece.visual_x, piece.visual_y) * block_size;
+                        painter.circle_filled(
+                            origin + piece.center_of_mass() * block_size,
+                            PIECE_MARKER_RADIUS,
+                            Color32::RED,
+                        );
+                        let center = origin + piece.rotation_center() * block_size;
+                        let stroke = egui::Stroke::new(1.5, Color32::WHITE);
+                        for arm in [egui::vec2(1.0, 1.0), egui::vec2(1.0, -1.0)] {
+                            let arm = arm * PIECE_MARKER_RADIUS;
+                            painter.line_segment([center - arm, center + arm], stroke);
+                        }
+                    }
+                }
+            }
+
+            if let Some(asked) = self.hard_drop_pending {
+                if asked.elapsed() < HARD_DROP_CONFIRM_WINDOW && !self.game_over {
+                    let piece = &self.current_piece;
+                    painter.text(
+                        response.rect.min
+                            + egui::Vec2::new(
+                                (piece.x as f32 + piece.shape[0].len() as f32 / 2.0) * block_size,
+                                self.ghost_y() as f32 * block_size - 4.0,
+                            ),
+                        egui::Align2::CENTER_BOTTOM,
+                        t.get("confirm_drop"),
+                        egui::FontId::proportional(16.0),
+                        Color32::WHITE,
+                    );
+                }
+            }
+
+            if let Some((name, at)) = self.opener {
+                let fade = 1.0 - at.elapsed().as_secs_f32() / OPENER_FLASH_DURATION.as_secs_f32();
+                if fade > 0.0 {
+                    painter.text(
+                        response.rect.center_top() + egui::Vec2::new(0.0, 3.0 * block_size),
+                        egui::Align2::CENTER_CENTER,
+                        t.fill("opener_detected", &[&name]),
+                        egui::FontId::proportional(22.0),
+                        Color32::YELLOW.gamma_multiply(fade),
+                    );
+                }
+            }
+
+            for popup in &self.score_popups {
+                let age = popup.born.elapsed().as_secs_f32();
+                let fade = 1.0 - age / SCORE_POPUP_DURATION.as_secs_f32();
+                painter.text(
+                    response.rect.center()
+                        + egui::Vec2::new(0.0, popup.offset - popup.rise_speed * age),
+                    egui::Align2::CENTER_CENTER,
+                    &popup.text,
+                    egui::FontId::proportional(20.0),
+                    Color32::WHITE.gamma_multiply(fade.max(0.0)),
+                );
+            }
+
+            // The walkthrough sits along the bottom of the board, pointing at the key to press
+            if let (Some(step), false) = (self.tutorial_step, self.game_over) {
+                let strip = egui::Rect::from_min_max(
+                    response.rect.left_bottom() - egui::Vec2::new(0.0, 3.0 * block_size),
+                    response.rect.right_bottom(),
+                );
+                painter.rect_filled(strip, 0.0, Color32::from_black_alpha(170));
+                painter.text(
+                    strip.center_top() + egui::Vec2::new(0.0, 0.9 * block_size),
+                    egui::Align2::CENTER_CENTER,
+                    t.get(step.instruction_key()),
+                    egui::FontId::proportional(14.0),
+                    Color32::WHITE,
+                );
+                if let Some(label) = step.key_label() {
+                    let galley = painter.layout_no_wrap(
+                        label.to_string(),
+                        egui::FontId::monospace(14.0),
+                        Color32::WHITE,
+                    );
+                    let key = egui::Rect::from_center_size(
+                        strip.center() + egui::Vec2::new(0.0, 0.6 * block_size),
+                        galley.size() + egui::Vec2::new(12.0, 6.0),
+                    );
+                    painter.rect_stroke(key, 3.0, egui::Stroke::new(1.5, Color32::WHITE));
+                    painter.galley(key.center() - galley.size() / 2.0, galley);
+                    painter.arrow(
+                        key.left_center() - egui::Vec2::new(40.0, 0.0),
+                        egui::Vec2::new(32.0, 0.0),
+                        egui::Stroke::new(2.0, Color32::YELLOW),
+                    );
+                }
+            }
+
+            if self.debug_overlay {
+                let galley = painter.layout_no_wrap(
+                    format!(
+                        "holes: {}\naggregate height: {}\nbumpiness: {}\nmax height: {}\n\
+                         filled: {} ({:.0}%)",
+                        self.board_holes(),
+                        self.aggregate_height(),
+                        self.bumpiness(),
+                        self.max_height(),
+                        self.pieces_on_board(),
+                        self.board_filled_fraction() * 100.0,
+                    ),
+                    egui::FontId::monospace(12.0),
+                    Color32::WHITE,
+                );
+                let text_pos = response.rect.min + egui::Vec2::splat(6.0);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(text_pos, galley.size()).expand(3.0),
+                    2.0,
+                    Color32::from_black_alpha(160),
+                );
+                painter.galley(text_pos, galley);
+            }
+
+            if self.paused {
+                painter.text(
+                    response.rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    t.get("pause"),
+                    egui::FontId::proportional(28.0),
+                    Color32::WHITE,
+                );
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.isometric)) {
+                self.isometric = !self.isometric;
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.grid)) {
+                self.config.show_grid = !self.config.show_grid;
+            }
+
+            if ctx.input(|i| keys::first_press(i, &self.config.key_bindings.rewind)) {
+                self.rewind();
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.placements)) {
+                self.placement_preview = !self.placement_preview;
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.debug)) {
+                self.debug_overlay = !self.debug_overlay;
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.stats)) {
+                self.show_stats = !self.show_stats;
+            }
+
+            // egui has no key for `?`, so look for it as typed text
+            let question_mark = ctx.input(|i| {
+                i.events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::Text(text) if text == "?"))
+            });
+            if question_mark {
+                self.show_controls = !self.show_controls;
+                self.controls_close_at = None;
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.pause)) && !self.game_over {
+                self.toggle_pause();
+            }
+
+            if ctx.input(|i| keys::pressed(i, &self.config.key_bindings.fullscreen)) {
+                self.set_fullscreen(frame, !self.fullscreen);
+            }
+
+            if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                if self.paused {
+                    self.toggle_pause();
+                } else if self.fullscreen {
+                    self.set_fullscreen(frame, false);
+                }
+            }
+
+            // A piece sliding in from above can't be moved until it arrives, but
+            // a key pressed meanwhile acts as soon as it does
+            let controllable = !self.current_piece.entering();
+            ctx.input(|i| self.hold_movement_keys(i, controllable));
+
+            let bindings = self.config.key_bindings.clone();
+            let rotation_press = ctx.input(|i| {
+                keys::first_press(i, &bindings.rotate)
+                    || keys::first_press(i, &bindings.rotate_reverse)
+            });
+            if controllable && rotation_press {
+                self.finesse_presses += 1;
+            }
+            // A press that already turned or held a piece as it spawned is used up
+            let rotation = controllable && !self.rotation_key_spent;
+            if rotation && ctx.input(|i| keys::first_press(i, &bindings.rotate)) {
+                self.apply_input(InputEvent::Rotate);
+                (self.rotation_key_spent, self.queued_rotation) = (true, None);
+            }
+
+            if rotation && ctx.input(|i| keys::first_press(i, &bindings.rotate_reverse)) {
+                self.apply_input(InputEvent::RotateReverse);
+                (self.rotation_key_spent, self.queued_rotation) = (true, None);
+            }
+
+            let hold = ctx.input(|i| keys::first_press(i, &bindings.hold));
+            if controllable && !self.hold_key_spent && hold {
+                self.apply_input(InputEvent::Hold);
+                (self.hold_key_spent, self.queued_hold) = (true, false);
+            }
+
+            // With confirmation on, the first hard drop press only asks; a second
+            // one within the window drops, and any other key calls it off. Holding
+            // the key down doesn't count as pressing it twice
+            let other_key = ctx.input(|i| {
+                i.events.iter().any(|event| {
+                    matches!(event, egui::Event::Key { key, pressed: true, .. } if !bindings.hard_drop.contains(key))
+                })
+            });
+            if other_key {
+                self.hard_drop_pending = None;
+            }
+            // Hard drop only brings the piece down in training wheels, and Enter
+            // places it. Both go through soft drops and locks so replays still play
+            // back
+            if self.mode == GameMode::TrainingWheels {
+                if controllable && ctx.input(|i| keys::first_press(i, &bindings.hard_drop)) {
+                    while !self.awaiting_placement() && !self.game_over && !self.paused {
+                        self.apply_input(InputEvent::SoftDrop);
+                    }
+                }
+                let enter = ctx.input(|i| keys::first_press(i, &[egui::Key::Enter]));
+                if controllable && enter && self.awaiting_placement() {
+                    self.apply_input(InputEvent::Lock);
+                }
+            } else if controllable && ctx.input(|i| keys::first_press(i, &bindings.hard_drop)) {
+                let confirmed = self
+                    .hard_drop_pending
+                    .take()
+                    .is_some_and(|asked| asked.elapsed() < HARD_DROP_CONFIRM_WINDOW);
+                if !self.config.confirm_hard_drop || confirmed {
+                    self.apply_input(InputEvent::HardDrop);
+                } else {
+                    self.hard_drop_pending = Some(Instant::now());
+                }
+            }
+
+            egui::CollapsingHeader::new(t.get("stats"))
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label(t.fill(
+                        "efficiency",
+                        &[&format!("{:.0}", self.stats.efficiency() * 100.0)],
+                    ));
+                    ui.label(t.fill("finesse_errors", &[&self.stats.finesse_errors]));
+
+                    let points = self.lpm_points();
+                    let max_lpm = points.iter().map(|&(_, lpm)| lpm).fold(1.0, f32::max);
+                    ui.label(t.fill("lpm_peak", &[&format!("{:.0}", max_lpm)]));
+                    let (graph, graph_painter) = ui.allocate_painter(
+                        egui::Vec2::new(BOARD_WIDTH as f32 * BLOCK_SIZE, 60.0),
+                        egui::Sense::hover(),
+                    );
+                    graph_painter.rect_stroke(
+                        graph.rect,
+                        0.0,
+                        egui::Stroke::new(1.0, egui::Color32::GRAY),
+                    );
+                    let to_screen = |(age, lpm): (f32, f32)| {
+                        egui::pos2(
+                            graph.rect.right()
+                                - age / LPM_WINDOW.as_secs_f32() * graph.rect.width(),
+                            graph.rect.bottom() - lpm / max_lpm * graph.rect.height(),
+                        )
+                    };
+                    for pair in points.windows(2) {
+                        graph_painter.line_segment(
+                            [to_screen(pair[0]), to_screen(pair[1])],
+                            egui::Stroke::new(1.5, egui::Color32::LIGHT_GREEN),
+                        );
+                    }
+                });
+
+            ui.collapsing(t.get("high_scores"), |ui| self.draw_high_scores(ui, t));
+
+            ui.collapsing(t.get("settings"), |ui| {
+                egui::ComboBox::from_label(t.get("language"))
+                    .selected_text(self.config.language.native_name())
+                    .show_ui(ui, |ui| {
+                        for language in Language::ALL {
+                            ui.selectable_value(
+                                &mut self.config.language,
+                                language,
+                                language.native_name(),
+                            );
+                        }
+                    });
+                ui.add(
+                    egui::Slider::new(&mut self.config.slide_speed, 0.0..=60.0)
+                        .text(t.get("slide_speed")),
+                );
+                ui.collapsing(t.get("handling"), |ui| self.draw_handling_settings(ui, t));
+                ui.add(
+                    egui::Slider::new(&mut self.config.preview_count, 0..=PREVIEW_MAX as u8)
+                        .text(t.get("preview_count")),
+                );
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::Slider::new(
+                            &mut self.config.starting_garbage,
+                            0..=MAX_STARTING_GARBAGE,
+                        )
+                        .show_value(false),
+                    );
+                    ui.label(t.fill("starting_garbage", &[&self.config.starting_garbage]));
+                });
+                ui.checkbox(
+                    &mut self.config.placement_feedback,
+                    t.get("placement_feedback"),
+                );
+                ui.checkbox(&mut self.config.drop_preview, t.get("drop_preview"));
+                ui.checkbox(&mut self.config.detect_openers, t.get("detect_openers"));
+                #[cfg(feature = "sound")]
+                ui.checkbox(&mut self.config.sound_effects, t.get("sound_effects"));
+                ui.checkbox(&mut self.config.confirm_hard_drop, t.get("confirm_hard_drop"));
+                ui.checkbox(&mut self.config.mercy_rule, t.get("mercy_rule"));
+                ui.checkbox(
+                    &mut self.config.show_active_row_highlight,
+                    t.get("active_row_highlight"),
+                );
+                ui.checkbox(
+                    &mut self.config.adaptive_theme,
+                    t.get("follow_system_theme"),
+                );
+                ui.add_enabled_ui(!self.config.adaptive_theme, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(
+                            &mut self.config.theme,
+                            ThemePreset::Dark,
+                            t.get("dark"),
+                        );
+                        ui.selectable_value(
+                            &mut self.config.theme,
+                            ThemePreset::Light,
+                            t.get("light"),
+                        );
+                    });
+                });
+                egui::ComboBox::from_label(t.get("background_pattern"))
+                    .selected_text(format!("{:?}", self.config.background_pattern))
+                    .show_ui(ui, |ui| {
+                        for pattern in BackgroundPattern::ALL {
+                            ui.selectable_value(
+                                &mut self.config.background_pattern,
+                                pattern,
+                                format!("{:?}", pattern),
+                            );
+                        }
+                    });
+                ui.checkbox(&mut self.config.show_grid, t.get("show_grid"));
+                ui.checkbox(&mut self.config.show_axis_labels, t.get("show_axis_labels"));
+                ui.checkbox(&mut self.config.show_metronome, t.get("show_metronome"));
+                ui.add_enabled_ui(self.config.show_grid, |ui| {
+                    egui::ComboBox::from_label(t.get("grid_style"))
+                        .selected_text(format!("{:?}", self.config.grid_style))
+                        .show_ui(ui, |ui| {
+                            for style in GridStyle::ALL {
+                                ui.selectable_value(
+                                    &mut self.config.grid_style,
+                                    style,
+                                    format!("{:?}", style),
+                                );
+                            }
+                        });
+                });
+                ui.checkbox(&mut self.config.piece_shadow, t.get("piece_shadow"));
+                ui.checkbox(&mut self.config.show_piece_labels, t.get("show_piece_labels"));
+                egui::ComboBox::from_label(t.get("render_style"))
+                    .selected_text(format!("{:?}", self.config.render_style))
+                    .show_ui(ui, |ui| {
+                        for style in RenderStyle::ALL {
+                            ui.selectable_value(
+                                &mut self.config.render_style,
+                                style,
+                                format!("{:?}", style),
+                            );
+                        }
+                    });
+                ui.checkbox(&mut self.config.reduced_motion, t.get("reduce_motion"));
+                ui.checkbox(&mut self.config.screen_reader, t.get("screen_reader"));
+                ui.checkbox(&mut self.config.power_ups, t.get("power_ups"));
+                ui.checkbox(&mut self.config.strict_mode, t.get("strict_mode"));
+                ui.checkbox(&mut self.show_move_log, t.get("show_move_log"));
+                if ui
+                    .checkbox(&mut self.config.manual_drop, t.get("manual_drop"))
+                    .changed()
+                {
+                    self.restart();
+                }
+                ui.checkbox(&mut self.ai_mode, t.get("let_bot_play"));
+                if ui.button(t.get("start_tutorial")).clicked() {
+                    self.config.shown_tips.clear();
+                    self.mode = GameMode::Tutorial;
+                    self.restart();
+                }
+                if ui.button(t.get("run_benchmark")).clicked() {
+                    self.start_benchmark();
+                }
+                ui.checkbox(&mut self.config.king_of_the_hill, t.get("king_of_the_hill"));
+                if ui.button(t.get("play_training_wheels")).clicked() {
+                    self.challenge = None;
+                    self.mode = GameMode::TrainingWheels;
+                    self.restart();
+                }
+                if ui.button(t.get("play_daily")).clicked() {
+                    self.challenge = None;
+                    self.mode = GameMode::DailyChallenge;
+                    self.restart();
+                }
+                if ui.button(t.get("play_online")).clicked() {
+                    self.lobby = Some(Lobby::new(
+                        self.config.username.clone(),
+                        self.config.language,
+                    ));
+                }
+                if ui.button(t.get("play_four_player")).clicked() {
+                    self.four_player = Some(Box::new(FourPlayerGame::new(self.config.clone())));
+                }
+                egui::ComboBox::from_label(t.get("bot_difficulty"))
+                    .selected_text(format!("{:?}", self.config.bot.difficulty))
+                    .show_ui(ui, |ui| {
+                        for difficulty in [
+                            Difficulty::Easy,
+                            Difficulty::Medium,
+                            Difficulty::Hard,
+                            Difficulty::Perfect,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.config.bot.difficulty,
+                                difficulty,
+                                format!("{:?}", difficulty),
+                            );
+                        }
+                    });
+                let speed = (self.config.gravity_curve, self.config.speed_mode);
+                egui::ComboBox::from_label(t.get("speed_mode"))
+                    .selected_text(format!("{:?}", self.config.speed_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in SpeedMode::ALL {
+                            ui.selectable_value(
+                                &mut self.config.speed_mode,
+                                mode,
+                                format!("{:?}", mode),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label(t.get("gravity_curve"))
+                    .selected_text(format!("{:?}", self.config.gravity_curve))
+                    .show_ui(ui, |ui| {
+                        for curve in GravityCurve::ALL {
+                            ui.selectable_value(
+                                &mut self.config.gravity_curve,
+                                curve,
+                                format!("{:?}", curve),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label(t.get("scoring"))
+                    .selected_text(format!("{:?}", self.config.scoring))
+                    .show_ui(ui, |ui| {
+                        for scoring in ScoringSystem::ALL {
+                            ui.selectable_value(
+                                &mut self.config.scoring,
+                                scoring,
+                                format!("{:?}", scoring),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label(t.get("rotation"))
+                    .selected_text(format!("{:?}", self.config.rotation_system))
+                    .show_ui(ui, |ui| {
+                        for system in RotationSystem::ALL {
+                            ui.selectable_value(
+                                &mut self.config.rotation_system,
+                                system,
+                                format!("{:?}", system),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label(t.get("randomizer"))
+                    .selected_text(format!("{:?}", self.config.randomizer))
+                    .show_ui(ui, |ui| {
+                        for randomizer in Randomizer::ALL {
+                            ui.selectable_value(
+                                &mut self.config.randomizer,
+                                randomizer,
+                                format!("{:?}", randomizer),
+                            );
+                        }
+                    });
+                if (self.config.gravity_curve, self.config.speed_mode) != speed {
+                    self.refresh_speed();
+                }
+            });
+
+            ui.collapsing(t.get("challenges"), |ui| {
+                for challenge in challenge::builtin() {
+                    let text = t.fill(
+                        "challenge_entry",
+                        &[
+                            &challenge.name,
+                            &challenge.target_lines,
+                            &challenge.max_pieces,
+                        ],
+                    );
+                    if ui.button(text).clicked() {
+                        let network = self.network.take();
+                        *self = TetrisGame::as_challenge(self.config.clone(), challenge.clone());
+                        self.network = network;
+                    }
+                }
+                if self.challenge.is_some() && ui.button(t.get("back_to_marathon")).clicked() {
+                    self.challenge = None;
+                    self.restart();
+                }
+            });
+
+            if self.ai_mode {
+                ui.collapsing(t.get("bot_settings"), |ui| {
+                    let bot = &mut self.config.bot;
+                    ui.add(egui::Slider::new(&mut bot.w_holes, -2.0..=2.0).text(t.get("holes")));
+                    ui.add(
+                        egui::Slider::new(&mut bot.w_bumpiness, -2.0..=2.0)
+                            .text(t.get("bumpiness")),
+                    );
+                    ui.add(egui::Slider::new(&mut bot.w_height, -2.0..=2.0).text(t.get("height")));
+                    ui.add(
+                        egui::Slider::new(&mut bot.w_lines_cleared, -2.0..=2.0)
+                            .text(t.get("lines_cleared")),
+                    );
+                    if ui.button(t.get("reset_to_default")).clicked() {
+                        *bot = AiBot {
+                            difficulty: bot.difficulty,
+                            ..AiBot::default()
+                        };
+                    }
+                });
+            }
+        });
+
+        let mut show_stats = self.show_stats;
+        if let Some(kind) = self.tutorial.current() {
+            egui::Window::new("tutorial_tip")
+                .title_bar(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 60.0))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let (swatch, _) =
+                            ui.allocate_exact_size(egui::Vec2::splat(16.0), egui::Sense::hover());
+                        ui.painter()
+                            .rect_filled(swatch, 2.0, self.theme.piece_color(kind));
+                        ui.label(t.get(tutorial::tip_key(kind)));
+                    });
+                });
+        }
+
+        egui::Window::new(t.get("session_stats"))
+            .open(&mut show_stats)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.monospace(self.stats.summary(t));
+                ui.horizontal(|ui| {
+                    if ui.button(t.get("reset_stats")).clicked() {
+                        self.reset_stats();
+                    }
+                    if ui.button(t.get("export")).clicked() {
+                        if let Err(err) = self.export_stats() {
+                            self.warnings
+                                .push(format!("{:#}", err.context("failed to export stats")));
+                        }
+                    }
+                });
+            });
+        self.show_stats = show_stats;
+
+        if matches!(&self.game_over_anim, Some(anim) if anim.current_row < 0) {
+            self.draw_end_screen(ctx, t);
+        }
+
+        if self
+            .controls_close_at
+            .is_some_and(|close_at| Instant::now() >= close_at)
+        {
+            self.show_controls = false;
+            self.controls_close_at = None;
+        }
+        if self.show_controls {
+            let window = egui::Window::new(t.get("controls"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    egui::Grid::new("controls_grid")
+                        .num_columns(2)
+                        .spacing([16.0, 4.0])
+                        .show(ui, |ui| {
+                            for (keys, action) in self.config.key_bindings.controls() {
+                                ui.monospace(keys);
+                                ui.label(t.get(action));
+                                ui.end_row();
+                            }
+                        });
+                });
+            let clicked_outside = window.is_some_and(|window| {
+                ctx.input(|i| {
+                    i.pointer.any_click()
+                        && i.pointer
+                            .interact_pos()
+                            .is_some_and(|pos| !window.response.rect.contains(pos))
+                })
+            });
+            if clicked_outside {
+                self.show_controls = false;
+                self.controls_close_at = None;
+            }
+        }
+
+        self.announce_events(ctx);
+        #[cfg(feature = "sound")]
+        if let (Some(sound), Some(kind)) = (&self.sound, self.lock_sound.take()) {
+            sound.play_lock(kind);
+        }
+        ctx.request_repaint();
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.config.read_only {
+            return;
+        }
+        if let Err(err) = self.config.save() {
+            tracing::error!("failed to save config: {err:#}");
+        }
+    }
+}
+
+// Clockwise from the top left, for drawing a cell as a polygon
+fn corners(rect: egui::Rect) -> [egui::Pos2; 4] {
+    [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ]
+}
+
+// Decoded once at startup and kept on the GPU
+fn load_background_image(
+    ctx: &egui::Context,
+    path: &std::path::Path,
+) -> anyhow::Result<egui::TextureHandle> {
+    let image = image::open(path)
+        .with_context(|| format!("failed to load the background image {}", path.display()))?
+        .to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+    Ok(ctx.load_texture("board_background", image, egui::TextureOptions::LINEAR))
+}
+
+// The middle of an image scaled to cover `target`, cropping whichever sides overhang
+fn cover_uv(image: egui::Vec2, target: egui::Vec2) -> egui::Rect {
+    let (image_aspect, target_aspect) = (image.x / image.y, target.x / target.y);
+    let visible = if image_aspect > target_aspect {
+        egui::vec2(target_aspect / image_aspect, 1.0)
+    } else {
+        egui::vec2(1.0, image_aspect / target_aspect)
+    };
+    egui::Rect::from_center_size(egui::pos2(0.5, 0.5), visible)
+}
+
+pub(crate) fn open_window(
+    title: &str,
+    create: impl FnOnce(&eframe::CreationContext) -> Box<dyn eframe::App> + 'static,
+) -> anyhow::Result<()> {
+    let native_options = eframe::NativeOptions {
+        follow_system_theme: true,
+        ..eframe::NativeOptions::default()
+    };
+    eframe::run_native(
+        title,
+        native_options,
+        Box::new(move |cc| {
+            crate::locale::install_fallback_fonts(&cc.egui_ctx);
+            create(cc)
+        }),
+    )
+    .map_err(|err| anyhow::anyhow!("{err}"))
+    .context("failed to open the window")
+}
+
+pub(crate) fn play(args: PlayArgs, log_error: Option<String>) -> anyhow::Result<()> {
+    open_window("Tetris", move |cc| {
+        // A broken config shouldn't keep the game from starting
+        let (config, config_error) = match Config::load().context("failed to load config") {
+            Ok(config) => (config, None),
+            Err(err) => {
+                let mut config = Config::default();
+                let kept = match Config::back_up_broken() {
+                    Ok(backup) => format!("kept it as {}", backup.display()),
+                    Err(err) => {
+                        config.read_only = true;
+                        format!("{err:#}, so it won't be saved over")
+                    }
+                };
+                (config, Some(format!("{err:#}; {kept}")))
+            }
+        };
+        let warnings: Vec<String> = config_error.into_iter().chain(log_error).collect();
+        for warning in &warnings {
+            tracing::warn!("{warning}");
+        }
+        let spectated = args.spectated();
+        if !spectated.is_empty() {
+            let theme = config.theme.theme();
+            return Box::new(SpectatorClient::new(spectated, theme, config.language));
+        }
+        let mut game = TetrisGame::new(config);
+        game.warnings = warnings;
+        // No audio device just means a quiet game, so it's only logged
+        #[cfg(feature = "sound")]
+        match SoundEngine::start() {
+            Ok(sound) => game.sound = Some(Arc::new(sound)),
+            Err(err) => tracing::warn!("{err:#}"),
+        }
+        if let Some(path) = &game.config.background_image {
+            match load_background_image(&cc.egui_ctx, path) {
+                Ok(texture) => game.background_image = Some(texture),
+                Err(err) => game.warnings.push(format!("{err:#}")),
+            }
+        }
+        if game.config.games_played == 0 {
+            game.show_controls = true;
+            game.controls_close_at = Some(Instant::now() + FIRST_GAME_CONTROLS_DURATION);
+        }
+        let (username, language) = (game.config.username.clone(), game.config.language);
+        game.lobby = args
+            .network_mode()
+            .map(|mode| Lobby::connecting(username, language, mode));
+        Box::new(game)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letting_go_while_a_piece_enters_ends_the_hold() {
+        let mut game = TetrisGame::new(Config::default());
+        // Matching focus, since egui lets go of every key when focus changes
+        let frame = |events: Vec<egui::Event>| {
+            let input = egui::InputState::default();
+            let raw = egui::RawInput {
+                events,
+                focused: input.focused,
+                ..Default::default()
+            };
+            input.begin_frame(raw, false)
+        };
+        let press = |key| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let (x, y) = (game.current_piece.x, game.current_piece.y);
+        let keys = vec![press(egui::Key::ArrowLeft), press(egui::Key::ArrowDown)];
+        game.hold_movement_keys(&frame(keys), false);
+        assert!(game.shift.is_some() && game.soft_drop.is_some());
+        // Both keys are back up before the piece has arrived
+        game.hold_movement_keys(&frame(vec![]), false);
+        assert!(game.shift.is_none() && game.soft_drop.is_none());
+        game.hold_movement_keys(&frame(vec![]), true);
+        assert_eq!((game.current_piece.x, game.current_piece.y), (x, y));
+    }
+
+    #[test]
+    fn background_images_are_cropped_to_cover_the_board() {
+        let board = egui::vec2(300.0, 600.0);
+        let wide = cover_uv(egui::vec2(1000.0, 500.0), board);
+        assert_eq!(
+            wide,
+            egui::Rect::from_min_max(egui::pos2(0.375, 0.0), egui::pos2(0.625, 1.0))
+        );
+        let tall = cover_uv(egui::vec2(100.0, 400.0), board);
+        assert_eq!(
+            tall,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.25), egui::pos2(1.0, 0.75))
+        );
+        assert_eq!(
+            cover_uv(board, board),
+            egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn axis_labels_get_room_for_two_digit_rows() {
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            let font = egui::FontId::monospace(AXIS_LABEL_SIZE);
+            let widest = ctx.fonts(|fonts| {
+                fonts
+                    .layout_no_wrap("19".to_string(), font, Color32::WHITE)
+                    .size()
+                    .x
+            });
+            assert!(TetrisGame::axis_label_space(ctx) > widest);
+        });
+    }
+
+    #[test]
+    fn screen_reader_announcements_reach_egui() {
+        let mut game = TetrisGame::new(Config {
+            screen_reader: true,
+            ..Config::default()
+        });
+        for row in &mut game.board[16..] {
+            row.fill(CellState::Garbage);
+        }
+        game.apply_clears(game.detect_clears());
+        // Stacked up to where the next piece spawns, so the hard drop tops out
+        for row in &mut game.board[..BOARD_HEIGHT - 1] {
+            row[..BOARD_WIDTH - 1].fill(CellState::Garbage);
+        }
+        game.hard_drop();
+        assert!(game.game_over);
+        game.finish_game();
+
+        let ctx = egui::Context::default();
+        let output = ctx.run(egui::RawInput::default(), |ctx| game.announce_events(ctx));
+        assert!(ctx.options(|options| options.screen_reader));
+        // Lines cleared, the new score, then game over
+        assert_eq!(output.platform_output.events.len(), 3);
+        assert!(game.announcements.is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::config::Config;
+use crate::game::{CellState, InputEvent, TetrisGame, TetrominoType};
+
+// A game driven from JavaScript, one action at a time
+#[wasm_bindgen]
+pub struct GameHandle {
+    game: TetrisGame,
+}
+
+// What `step` hands back, as JSON
+#[derive(Serialize)]
+struct GameState {
+    board: Vec<u8>,
+    piece: Option<TetrominoType>,
+    x: usize,
+    y: isize,
+    rotation: u8,
+    held: Option<TetrominoType>,
+    score: u32,
+    level: u32,
+    lines: u32,
+    game_over: bool,
+}
+
+#[wasm_bindgen]
+pub fn new_game() -> GameHandle {
+    let mut game = TetrisGame::new(Config::default());
+    game.tutorial_step = None;
+    GameHandle { game }
+}
+
+// `action` indexes InputEvent::ALL: 0 left, 1 right, 2 soft drop, 3 hard drop,
+// 4 rotate, 5 rotate back, 6 hold, 7 gravity, 8 lock. Anything else just reads
+// the state
+#[wasm_bindgen]
+pub fn step(handle: &mut GameHandle, action: u8) -> JsValue {
+    let game = &mut handle.game;
+    if let Some(&event) = InputEvent::ALL.get(action as usize) {
+        if !game.game_over {
+            game.apply_input(event);
+        }
+    }
+    let piece = &game.current_piece;
+    let state = GameState {
+        board: cell_indices(game),
+        piece: (!game.game_over).then_some(piece.kind),
+        x: piece.x,
+        y: piece.y,
+        rotation: piece.rotation,
+        held: game.held_piece,
+        score: game.score,
+        level: game.level,
+        lines: game.lines_cleared,
+        game_over: game.game_over,
+    };
+    JsValue::from_str(&serde_json::to_string(&state).unwrap_or_default())
+}
+
+// The locked cells, row-major from the top
+#[wasm_bindgen]
+pub fn get_board(handle: &GameHandle) -> js_sys::Uint8Array {
+    js_sys::Uint8Array::from(cell_indices(&handle.game).as_slice())
+}
+
+// 0 for empty, 1 to 7 for the pieces in TetrominoType::ALL order and 8 for garbage
+fn cell_indices(game: &TetrisGame) -> Vec<u8> {
+    game.board
+        .iter()
+        .flatten()
+        .map(|&cell| match cell {
+            CellState::Empty => 0,
+            CellState::Filled(kind) => {
+                TetrominoType::ALL
+                    .iter()
+                    .position(|&k| k == kind)
+                    .unwrap_or(0) as u8
+                    + 1
+            }
+            CellState::Garbage => TetrominoType::ALL.len() as u8 + 1,
+        })
+        .collect()
+}
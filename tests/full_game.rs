@@ -0,0 +1,20 @@
+use tetris_rs::headless::HeadlessDriver;
+
+const SEED: u64 = 100;
+
+// Everything from spawning to line clears, scoring and level ups has to hold
+// together for the bot to get this far
+#[test]
+fn bot_plays_a_hundred_lines() {
+    let mut driver = HeadlessDriver::seeded(SEED);
+    while driver.lines_cleared() < 100 && driver.pieces() < 1000 && driver.step() {}
+
+    assert!(
+        !driver.game_over(),
+        "topped out after {} lines",
+        driver.lines_cleared()
+    );
+    assert!(driver.lines_cleared() >= 100);
+    assert!(driver.score() > 0);
+    assert!(driver.level() >= 10);
+}